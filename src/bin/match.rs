@@ -0,0 +1,116 @@
+use chess_core::tournament::openings::load_openings;
+use chess_core::tournament::stats::{Sprt, SprtStatus};
+use chess_core::tournament::*;
+use chess_core::uci::client::UciClient;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::process::exit;
+
+const USAGE: &str = "usage: chess-core-match <engine> <engine> [--games N] [--tc BASE+INC] \
+[--openings FILE.epd|FILE.pgn] [--pgn OUT.pgn] [--sprt ELO0 ELO1] [--resign CP MOVES] \
+[--draw CP MOVES FROM_MOVE]";
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mut paths = Vec::new();
+    let mut config = MatchConfig {
+        openings: Vec::new(),
+        games: 2,
+        time_control: TimeControl::parse("10+0.1").unwrap(),
+        adjudication: Adjudication::default(),
+        sprt: None,
+    };
+    let mut pgn = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = |count: usize| -> Vec<&str> {
+            let values: Vec<&str> = args.by_ref().take(count).map(String::as_str).collect();
+            if values.len() < count {
+                fail(USAGE);
+            }
+            values
+        };
+        let number = |text: &str| text.parse().unwrap_or_else(|_| fail(USAGE));
+        match arg.as_str() {
+            "--games" => config.games = number(value(1)[0]) as u32,
+            "--tc" => {
+                config.time_control = TimeControl::parse(value(1)[0]).unwrap_or_else(|| fail(USAGE))
+            }
+            "--openings" => {
+                config.openings = load_openings(Path::new(value(1)[0]))
+                    .unwrap_or_else(|error| fail(&error.to_string()))
+            }
+            "--pgn" => {
+                let path = value(1)[0];
+                pgn = Some(File::create(path).unwrap_or_else(|error| fail(&error.to_string())))
+            }
+            "--sprt" => {
+                let values = value(2);
+                config.sprt = Some(Sprt::new(number(values[0]), number(values[1])));
+            }
+            "--resign" => {
+                let values = value(2);
+                config.adjudication.resign_score = Some(number(values[0]) as i32);
+                config.adjudication.resign_moves = number(values[1]) as u32;
+            }
+            "--draw" => {
+                let values = value(3);
+                config.adjudication.draw_score = Some(number(values[0]) as i32);
+                config.adjudication.draw_moves = number(values[1]) as u32;
+                config.adjudication.draw_move_number = number(values[2]) as u32;
+            }
+            _ if arg.starts_with("--") => fail(USAGE),
+            _ => paths.push(arg.as_str()),
+        }
+    }
+    if paths.len() != 2 {
+        fail(USAGE);
+    }
+    let spawn = |path: &str| {
+        UciClient::spawn(path).unwrap_or_else(|error| fail(&format!("{}: {}", path, error)))
+    };
+    let mut engines = [spawn(paths[0]), spawn(paths[1])];
+
+    let score = run_match(&mut engines, &config, |game, score| {
+        let games = score.games();
+        println!(
+            "game {}: {} - {} {} ({})",
+            games,
+            game.white,
+            game.black,
+            game.result.as_str(),
+            game.termination
+        );
+        let (elo, error) = score.elo().unwrap();
+        print!(
+            "score {}-{}-{}, elo {:.1} +/- {:.1}",
+            score.wins, score.losses, score.draws, elo, error
+        );
+        if let Some(sprt) = config.sprt {
+            let (lower, upper) = sprt.bounds();
+            print!(", llr {:.2} ({:.2}, {:.2})", sprt.llr(score), lower, upper);
+        }
+        println!();
+        if let Some(pgn) = pgn.as_mut() {
+            if let Err(error) = pgn.write_all(game.to_pgn("chess-core match", games).as_bytes()) {
+                fail(&error.to_string());
+            }
+        }
+    });
+    if let Some(sprt) = config.sprt {
+        match sprt.status(&score) {
+            SprtStatus::AcceptH0 => println!("sprt: H0 accepted"),
+            SprtStatus::AcceptH1 => println!("sprt: H1 accepted"),
+            SprtStatus::Continue => println!("sprt: inconclusive"),
+        }
+    }
+    for engine in engines {
+        let _ = engine.quit();
+    }
+}
@@ -0,0 +1,12 @@
+use chess_core::uci::engine::run;
+use std::io;
+
+/// Speaks UCI on the standard input and output. With the `bench` argument, prints the node
+/// count of the bench and exits.
+fn main() {
+    if std::env::args().nth(1).as_deref() == Some("bench") {
+        run("bench\nquit\n".as_bytes(), io::stdout());
+        return;
+    }
+    run(io::stdin().lock(), io::stdout());
+}
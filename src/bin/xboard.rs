@@ -0,0 +1,6 @@
+use chess_core::xboard::engine::run;
+use std::io;
+
+fn main() {
+    run(io::stdin().lock(), io::stdout());
+}
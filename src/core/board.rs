@@ -16,6 +16,9 @@ pub struct Board {
     pub(crate) en_passant: Option<Square>,
     pub(crate) can_castle_king_side: [bool; 2],
     pub(crate) can_castle_queen_side: [bool; 2],
+    /// File of the rook each castling right refers to, indexed by `[CastlingSide][Color]`.
+    pub(crate) castling_rook_file: [[u8; 2]; 2],
+    pub(crate) chess960: bool,
     pub(crate) side_to_play: Color,
     pub(crate) half_move_counter: u8,
 }
@@ -35,6 +38,8 @@ impl Board {
             en_passant: None,
             can_castle_king_side: [false, false],
             can_castle_queen_side: [false, false],
+            castling_rook_file: [[7; 2], [0; 2]],
+            chess960: false,
             side_to_play: WHITE,
             half_move_counter: 0,
         }
@@ -45,6 +50,8 @@ impl Board {
             side_to_play: WHITE,
             can_castle_king_side: [true; 2],
             can_castle_queen_side: [true; 2],
+            castling_rook_file: [[7; 2], [0; 2]],
+            chess960: false,
             en_passant: None,
             piece_of_color: [RANK_1 | RANK_2, RANK_7 | RANK_8],
             piece_of_type: [
@@ -59,6 +66,110 @@ impl Board {
         }
     }
 
+    /// Returns the Chess960 start position with Scharnagl number `index`, which must be below
+    /// 960. Position 518 is the standard start position.
+    pub fn from_chess960_position(index: u16) -> Board {
+        assert!(index < 960);
+        let mut back_rank: [Option<Piece>; 8] = [None; 8];
+        let mut n = index as usize;
+        back_rank[2 * (n % 4) + 1] = Some(BISHOP);
+        n /= 4;
+        back_rank[2 * (n % 4)] = Some(BISHOP);
+        n /= 4;
+        let free_files: Vec<usize> = (0..8).filter(|&file| back_rank[file].is_none()).collect();
+        back_rank[free_files[n % 6]] = Some(QUEEN);
+        n /= 6;
+        const KNIGHTS: [(usize, usize); 10] = [
+            (0, 1),
+            (0, 2),
+            (0, 3),
+            (0, 4),
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 3),
+            (2, 4),
+            (3, 4),
+        ];
+        let free_files: Vec<usize> = (0..8).filter(|&file| back_rank[file].is_none()).collect();
+        back_rank[free_files[KNIGHTS[n].0]] = Some(KNIGHT);
+        back_rank[free_files[KNIGHTS[n].1]] = Some(KNIGHT);
+        let free_files: Vec<usize> = (0..8).filter(|&file| back_rank[file].is_none()).collect();
+        back_rank[free_files[0]] = Some(ROOK);
+        back_rank[free_files[1]] = Some(KING);
+        back_rank[free_files[2]] = Some(ROOK);
+
+        let king_file = free_files[1] as u8;
+        let mut board = Board::empty(
+            Square::from_coords(king_file, 0),
+            Square::from_coords(king_file, 7),
+        );
+        for (file, piece) in back_rank.iter().enumerate() {
+            if let Some(piece) = *piece {
+                if piece != KING {
+                    let file_bb = BitBoard::from_square(Square::from_coords(file as u8, 0));
+                    board.put_piece_fast(piece, WHITE, file_bb);
+                    board.put_piece_fast(piece, BLACK, file_bb.shift(7 * UP));
+                }
+            }
+        }
+        board.put_piece_fast(PAWN, WHITE, RANK_2);
+        board.put_piece_fast(PAWN, BLACK, RANK_7);
+        board.can_castle_king_side = [true; 2];
+        board.can_castle_queen_side = [true; 2];
+        board.castling_rook_file = [[free_files[2] as u8; 2], [free_files[0] as u8; 2]];
+        board.chess960 = true;
+        board
+    }
+
+    /// Whether castling moves are written the Chess960 way, as the king capturing its own rook.
+    pub fn chess960(&self) -> bool {
+        self.chess960
+    }
+
+    pub fn set_chess960(&mut self, chess960: bool) {
+        self.chess960 = chess960;
+    }
+
+    pub fn can_castle(&self, color: Color, side: CastlingSide) -> bool {
+        match side {
+            KingSide => self.can_castle_king_side[color],
+            QueenSide => self.can_castle_queen_side[color],
+        }
+    }
+
+    /// The original square of the rook used to castle on `side`.
+    #[inline]
+    pub fn castling_rook_square(&self, color: Color, side: CastlingSide) -> Square {
+        let rank = match color {
+            WHITE => 0,
+            BLACK => 7,
+        };
+        Square::from_coords(self.castling_rook_file[side][color], rank)
+    }
+
+    /// King origin, king destination, rook origin and rook destination of castling on `side`.
+    /// The destinations are the same as in standard chess whatever the start position.
+    #[inline]
+    pub fn castling_squares(
+        &self,
+        color: Color,
+        side: CastlingSide,
+    ) -> (Square, Square, Square, Square) {
+        let rook_origin = self.castling_rook_square(color, side);
+        let rank = rook_origin.rank();
+        let (king_file, rook_file) = match side {
+            KingSide => (6, 5),
+            QueenSide => (2, 3),
+        };
+        (
+            self.king_pos[color],
+            Square::from_coords(king_file, rank),
+            rook_origin,
+            Square::from_coords(rook_file, rank),
+        )
+    }
+
     pub fn set_king_pos(&mut self, color: Color, square: Square) -> Result<(), ()> {
         if self.king_pos[color.opposite()] == square {
             return Err(());
@@ -160,10 +271,8 @@ impl Board {
     pub fn make_move(&mut self, m: Move) {
         self.en_passant = None;
         match m {
-            Move::CastleKingSideWhite => self.castle_king_side_white_fast(),
-            Move::CastleQueenSideWhite => self.castle_queen_side_white_fast(),
-            Move::CastleKingSideBlack => self.castle_king_side_black_fast(),
-            Move::CastleQueenSideBlack => self.castle_queen_side_black_fast(),
+            Move::CastleKingSideWhite | Move::CastleKingSideBlack => self.castle(KingSide),
+            Move::CastleQueenSideWhite | Move::CastleQueenSideBlack => self.castle(QueenSide),
             Move::Promotion {
                 origin,
                 dest,
@@ -179,11 +288,19 @@ impl Board {
                 dest,
                 capture,
             } => self.make_en_passant_move(origin, dest, capture),
-            Move::NullMove => return,
+            Move::NullMove => self.half_move_counter += 1,
         }
         self.side_to_play = self.side_to_play.opposite();
     }
 
+    /// Takes back `Move::NullMove`. `en_passant` is the en passant square before the null move,
+    /// which is the only state the null move loses.
+    pub fn unmake_null_move(&mut self, en_passant: Option<Square>) {
+        self.side_to_play = self.side_to_play.opposite();
+        self.en_passant = en_passant;
+        self.half_move_counter -= 1;
+    }
+
     fn make_normal_move(&mut self, origin: Square, dest: Square, piece: Piece) {
         let origin_bb = BitBoard::from_square(origin);
         let dest_bb = BitBoard::from(dest);
@@ -224,19 +341,14 @@ impl Board {
 
     #[inline]
     fn update_castling_rights(&mut self, both_squares: BitBoard) {
-        const CORNERS: BitBoard = BitBoard(BB_A1.0 | BB_H1.0 | BB_A8.0 | BB_H8.0);
-        if both_squares * CORNERS {
-            if both_squares * BB_A1 {
-                self.can_castle_queen_side[WHITE] = false;
-            }
-            if both_squares * BB_H1 {
-                self.can_castle_king_side[WHITE] = false;
-            }
-            if both_squares * BB_A8 {
-                self.can_castle_queen_side[BLACK] = false;
-            }
-            if both_squares * BB_H8 {
-                self.can_castle_king_side[BLACK] = false;
+        if both_squares * (RANK_1 | RANK_8) {
+            for color in [WHITE, BLACK] {
+                if both_squares * self.castling_rook_square(color, QueenSide) {
+                    self.can_castle_queen_side[color] = false;
+                }
+                if both_squares * self.castling_rook_square(color, KingSide) {
+                    self.can_castle_king_side[color] = false;
+                }
             }
         }
     }
@@ -252,39 +364,19 @@ impl Board {
         self.half_move_counter = 0;
     }
 
-    fn castle_king_side_white_fast(&mut self) {
-        self.king_pos[WHITE] = SQ_G1;
-        self.piece_of_color[WHITE] ^= BB_E1 | BB_F1 | BB_G1 | BB_H1;
-        self.piece_of_type[ROOK as usize] ^= BB_F1 | BB_H1;
-        self.can_castle_king_side[WHITE] = false;
-        self.can_castle_queen_side[WHITE] = false;
-        self.half_move_counter += 1;
-    }
-
-    fn castle_queen_side_white_fast(&mut self) {
-        self.king_pos[WHITE] = SQ_C1;
-        self.piece_of_color[WHITE] ^= BB_A1 | BB_C1 | BB_D1 | BB_E1;
-        self.piece_of_type[ROOK as usize] ^= BB_D1 | BB_A1;
-        self.can_castle_king_side[WHITE] = false;
-        self.can_castle_queen_side[WHITE] = false;
-        self.half_move_counter += 1;
-    }
-
-    fn castle_king_side_black_fast(&mut self) {
-        self.king_pos[BLACK] = SQ_G8;
-        self.piece_of_color[BLACK] ^= BB_E8 | BB_F8 | BB_G8 | BB_H8;
-        self.piece_of_type[ROOK as usize] ^= BB_F8 | BB_H8;
-        self.can_castle_king_side[BLACK] = false;
-        self.can_castle_queen_side[BLACK] = false;
-        self.half_move_counter += 1;
-    }
-
-    fn castle_queen_side_black_fast(&mut self) {
-        self.king_pos[BLACK] = SQ_C8;
-        self.piece_of_color[BLACK] ^= BB_A8 | BB_C8 | BB_D8 | BB_E8;
-        self.piece_of_type[ROOK as usize] ^= BB_D8 | BB_A8;
-        self.can_castle_king_side[BLACK] = false;
-        self.can_castle_queen_side[BLACK] = false;
+    fn castle(&mut self, side: CastlingSide) {
+        let us = self.side_to_play;
+        let (king_origin, king_dest, rook_origin, rook_dest) = self.castling_squares(us, side);
+        // in Chess960 the king or the rook may land on the other's origin, so remove both
+        // pieces before putting them back
+        self.piece_of_color[us] -= king_origin;
+        self.piece_of_color[us] -= rook_origin;
+        self.piece_of_type[ROOK as usize] -= rook_origin;
+        self.piece_of_color[us] |= BitBoard::from_square(king_dest) | rook_dest;
+        self.piece_of_type[ROOK as usize] |= BitBoard::from_square(rook_dest);
+        self.king_pos[us] = king_dest;
+        self.can_castle_king_side[us] = false;
+        self.can_castle_queen_side[us] = false;
         self.half_move_counter += 1;
     }
 
@@ -348,6 +440,217 @@ impl Board {
         false
     }
 
+    /// Returns whether `m` would put the opponent in check, without making the move.
+    pub fn gives_check(&self, m: Move) -> bool {
+        let us = self.side_to_play;
+        let enemy_king = self.king_pos[us.opposite()];
+        let (vacated, landed, piece, dest) = match m {
+            Move::NormalMove {
+                origin,
+                dest,
+                piece,
+            }
+            | Move::Promotion {
+                origin,
+                dest,
+                piece,
+            } => (
+                BitBoard::from_square(origin),
+                BitBoard::from(dest),
+                piece,
+                dest,
+            ),
+            Move::EnPassant {
+                origin,
+                dest,
+                capture,
+            } => (
+                BitBoard::from_square(origin) | capture,
+                BitBoard::from(dest),
+                PAWN,
+                dest,
+            ),
+            Move::CastleKingSideWhite
+            | Move::CastleQueenSideWhite
+            | Move::CastleKingSideBlack
+            | Move::CastleQueenSideBlack => {
+                let side = m.castling_side().unwrap();
+                let (king_origin, king_dest, rook_origin, rook_dest) =
+                    self.castling_squares(us, side);
+                (
+                    BitBoard::from_square(king_origin) | rook_origin,
+                    BitBoard::from_square(king_dest) | rook_dest,
+                    ROOK,
+                    rook_dest,
+                )
+            }
+            Move::NullMove => return false,
+        };
+
+        let our_piece = self.piece_of_color[us];
+        let occupancy = ((self.piece_of_color[0] | self.piece_of_color[1]) - vacated) | landed;
+        let mut rook_queen =
+            (our_piece & (self.piece_of_type(ROOK) | self.piece_of_type(QUEEN))) - vacated;
+        let mut bishop_queen =
+            (our_piece & (self.piece_of_type(BISHOP) | self.piece_of_type(QUEEN))) - vacated;
+        match piece {
+            PAWN => {
+                if pawn_attacks(us, dest) * enemy_king {
+                    return true;
+                }
+            }
+            KNIGHT => {
+                if knight_attacks(dest) * enemy_king {
+                    return true;
+                }
+            }
+            BISHOP => bishop_queen |= BitBoard::from_square(dest),
+            ROOK => rook_queen |= BitBoard::from_square(dest),
+            QUEEN => {
+                rook_queen |= BitBoard::from_square(dest);
+                bishop_queen |= BitBoard::from_square(dest);
+            }
+            KING => {}
+        }
+        // sliders are checked with the occupancy after the move, which covers both
+        // direct checks by the moved piece and checks discovered by vacating squares
+        rook_attacks(enemy_king, occupancy) * rook_queen
+            || bishop_attacks(enemy_king, occupancy) * bishop_queen
+    }
+
+    #[inline(always)]
+    pub fn king_pos(&self, color: Color) -> Square {
+        self.king_pos[color]
+    }
+
+    #[inline(always)]
+    pub fn occupancy(&self) -> BitBoard {
+        self.piece_of_color[WHITE] | self.piece_of_color[BLACK]
+    }
+
+    #[inline(always)]
+    fn kings(&self) -> BitBoard {
+        BitBoard::from_square(self.king_pos[WHITE]) | self.king_pos[BLACK]
+    }
+
+    /// Pieces of both colors attacking `sq`, with sliders seeing through everything not in `occupancy`.
+    pub fn attackers_to(&self, sq: Square, occupancy: BitBoard) -> BitBoard {
+        let rook_queen = self.piece_of_type(ROOK) | self.piece_of_type(QUEEN);
+        let bishop_queen = self.piece_of_type(BISHOP) | self.piece_of_type(QUEEN);
+        let pawn = self.piece_of_type(PAWN);
+        (knight_attacks(sq) & self.piece_of_type(KNIGHT))
+            | (pawn_attacks(WHITE, sq) & pawn & self.piece_of_color[BLACK])
+            | (pawn_attacks(BLACK, sq) & pawn & self.piece_of_color[WHITE])
+            | (king_attacks(sq) & self.kings())
+            | (rook_attacks(sq, occupancy) & rook_queen)
+            | (bishop_attacks(sq, occupancy) & bishop_queen)
+    }
+
+    /// Every square attacked by at least one piece of `color`.
+    pub fn attacks_by(&self, color: Color) -> BitBoard {
+        let our_piece = self.piece_of_color[color];
+        let occupancy = self.occupancy();
+        let pawns = our_piece & self.piece_of_type(PAWN);
+        let fwd_dir = color.fwd_dir();
+        let mut attacks = (pawns & FILE_A_I).shift(fwd_dir + LEFT)
+            | (pawns & FILE_H_I).shift(fwd_dir + RIGHT)
+            | king_attacks(self.king_pos[color]);
+
+        let mut remaining = our_piece & self.piece_of_type(KNIGHT);
+        while !remaining.empty() {
+            attacks |= knight_attacks(remaining.pop_lsb());
+        }
+        remaining = our_piece & (self.piece_of_type(ROOK) | self.piece_of_type(QUEEN));
+        while !remaining.empty() {
+            attacks |= rook_attacks(remaining.pop_lsb(), occupancy);
+        }
+        remaining = our_piece & (self.piece_of_type(BISHOP) | self.piece_of_type(QUEEN));
+        while !remaining.empty() {
+            attacks |= bishop_attacks(remaining.pop_lsb(), occupancy);
+        }
+        attacks
+    }
+
+    /// Enemy pieces giving check to the side to play.
+    pub fn checkers(&self) -> BitBoard {
+        let us = self.side_to_play;
+        self.attackers_to(self.king_pos[us], self.occupancy()) & self.piece_of_color[us.opposite()]
+    }
+
+    /// Neither side can mate: bare kings, a single minor piece, or only bishops all on squares of
+    /// the same color.
+    pub fn insufficient_material(&self) -> bool {
+        let heavy = self.piece_of_type(PAWN) | self.piece_of_type(ROOK) | self.piece_of_type(QUEEN);
+        if !heavy.empty() {
+            return false;
+        }
+        let knights = self.piece_of_type(KNIGHT);
+        let bishops = self.piece_of_type(BISHOP);
+        if (knights | bishops).num_squares() <= 1 {
+            return true;
+        }
+        knights.empty() && ((bishops & DARK_SQUARES).empty() || (bishops & LIGHT_SQUARES).empty())
+    }
+
+    /// Pieces of `color` that are pinned to their own king.
+    pub fn pinned(&self, color: Color) -> BitBoard {
+        self.pins(color).0
+    }
+
+    /// Enemy sliders pinning a piece of `color` to its king, i.e. the pinners of `pinned(color)`.
+    pub fn pinners(&self, color: Color) -> BitBoard {
+        self.pins(color).1
+    }
+
+    fn pins(&self, color: Color) -> (BitBoard, BitBoard) {
+        let king = self.king_pos[color];
+        let enemy_piece = self.piece_of_color[color.opposite()];
+        let occupancy = self.occupancy();
+        let rook_queen = self.piece_of_type(ROOK) | self.piece_of_type(QUEEN);
+        let bishop_queen = self.piece_of_type(BISHOP) | self.piece_of_type(QUEEN);
+        let mut snipers = enemy_piece
+            & ((rook_attacks_empty(king) & rook_queen)
+                | (bishop_attacks_empty(king) & bishop_queen));
+
+        let mut pinned = BitBoard::EMPTY;
+        let mut pinners = BitBoard::EMPTY;
+        while !snipers.empty() {
+            let sniper = snipers.pop_lsb();
+            let path = LINE_SEGMENT[king][sniper] & occupancy;
+            if path.num_squares() == 1 && path * self.piece_of_color[color] {
+                pinned |= path;
+                pinners |= BitBoard::from_square(sniper);
+            }
+        }
+        (pinned, pinners)
+    }
+
+    /// Returns the position mirrored vertically with the colors swapped, including the side to
+    /// play, castling rights and en passant square. Scores of symmetric evaluations are the
+    /// exact negation for the other color.
+    pub fn color_flipped(&self) -> Board {
+        let flip = |bb: BitBoard| BitBoard(bb.0.swap_bytes());
+        let flip_square = |sq: Square| Square(sq.0 ^ 56);
+        Board {
+            piece_of_color: [
+                flip(self.piece_of_color[BLACK]),
+                flip(self.piece_of_color[WHITE]),
+            ],
+            piece_of_type: self.piece_of_type.map(flip),
+            king_pos: [
+                flip_square(self.king_pos[BLACK]),
+                flip_square(self.king_pos[WHITE]),
+            ],
+            en_passant: self.en_passant.map(flip_square),
+            can_castle_king_side: [self.can_castle_king_side[1], self.can_castle_king_side[0]],
+            can_castle_queen_side: [self.can_castle_queen_side[1], self.can_castle_queen_side[0]],
+            castling_rook_file: self.castling_rook_file.map(|[white, black]| [black, white]),
+            chess960: self.chess960,
+            side_to_play: self.side_to_play.opposite(),
+            half_move_counter: self.half_move_counter,
+        }
+    }
+
     pub fn check_consistency(&self) -> bool {
         if self.piece_of_color[0] * self.piece_of_color[1] {
             eprintln!("inconsistent colors");
@@ -414,10 +717,18 @@ impl Display for Board {
 
 #[cfg(test)]
 mod tests {
+    use crate::core::bitboard::BitBoard;
     use crate::core::board::Board;
+    use crate::core::fen::{board_from_fen, to_string};
+    use crate::core::move_generator::MoveGenerator;
+    use crate::core::r#move::Move;
     use crate::core::square_constants::*;
+    use crate::core::zobrist_hash::{hash, hash_update};
+    use crate::core::CastlingSide::*;
     use crate::core::Color::*;
     use crate::core::Piece::*;
+    use rand::{RngCore, SeedableRng};
+    use std::collections::HashSet;
 
     #[test]
     pub fn test_display() {
@@ -452,4 +763,173 @@ mod tests {
         assert_eq!(board.under_check(WHITE), false);
         assert_eq!(board.under_check(BLACK), false);
     }
+
+    #[test]
+    pub fn insufficient_material() {
+        let insufficient = |fen| board_from_fen(fen).unwrap().insufficient_material();
+        assert!(insufficient("8/8/4k3/8/8/3K4/8/8 w - - 0 1"));
+        assert!(insufficient("8/8/4k3/8/8/3KN3/8/8 w - - 0 1"));
+        // bishops on light squares only
+        assert!(insufficient("8/5b2/4k3/8/8/3K4/4B3/5B2 b - - 0 1"));
+        assert!(!insufficient("8/6b1/4k3/8/8/3K4/4B3/8 b - - 0 1"));
+        assert!(!insufficient("8/8/4kn2/8/8/3K4/4B3/8 b - - 0 1"));
+        assert!(!insufficient("8/8/4k3/8/8/3K4/4P3/8 w - - 0 1"));
+        assert!(!Board::from_initial_position().insufficient_material());
+    }
+
+    #[test]
+    pub fn gives_check_special_moves() {
+        // discovered check by en passant: the capturing pawn leaves the a1-h8 diagonal
+        let board = board_from_fen("7k/8/8/3pP3/8/8/8/B3K3 w - d6 0 1").unwrap();
+        assert!(board.gives_check(Move::en_passant(SQ_E5, SQ_D6, SQ_D5)));
+
+        // promotion to a knight checks, promotion to a queen doesn't
+        let board = board_from_fen("8/2P5/8/8/8/8/8/k3K3 w - - 0 1").unwrap();
+        assert!(!board.gives_check(Move::promote(SQ_C7, SQ_C8, QUEEN)));
+        let board = board_from_fen("8/2P5/1k6/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(board.gives_check(Move::promote(SQ_C7, SQ_C8, KNIGHT)));
+        assert!(!board.gives_check(Move::promote(SQ_C7, SQ_C8, ROOK)));
+
+        // the rook gives check after castling
+        let board = board_from_fen("5k2/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(board.gives_check(Move::CastleKingSideWhite));
+    }
+
+    #[test]
+    pub fn gives_check_matches_make_move() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for i in 0..2000 {
+            let mut board = if i % 2 == 0 {
+                Board::from_initial_position()
+            } else {
+                Board::from_chess960_position((rng.next_u64() % 960) as u16)
+            };
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                for &m in mg.moves.iter() {
+                    let mut new_board = board;
+                    new_board.make_move(m);
+                    assert_eq!(
+                        board.gives_check(m),
+                        new_board.under_check(new_board.side_to_play),
+                        "{}\n{}",
+                        board,
+                        m
+                    );
+                }
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+    }
+
+    #[test]
+    pub fn null_move() {
+        let fen = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2";
+        let board = board_from_fen(fen).unwrap();
+        let mut new_board = board;
+        new_board.make_move(Move::NullMove);
+        assert_eq!(new_board.side_to_play, BLACK);
+        assert_eq!(new_board.en_passant(), None);
+        assert_eq!(new_board.half_move_counter, 1);
+        assert_eq!(
+            hash(&new_board),
+            hash_update(&board, hash(&board), Move::NullMove)
+        );
+
+        new_board.unmake_null_move(board.en_passant());
+        assert_eq!(new_board, board);
+    }
+
+    #[test]
+    pub fn chess960_start_positions() {
+        let standard = Board::from_chess960_position(518);
+        assert_eq!(
+            to_string(&standard, 1),
+            to_string(&Board::from_initial_position(), 1)
+        );
+        assert_eq!(
+            to_string(&Board::from_chess960_position(0), 1),
+            "bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w KQkq - 0 1"
+        );
+
+        let mut back_ranks = HashSet::new();
+        for index in 0..960 {
+            let board = Board::from_chess960_position(index);
+            let fen = to_string(&board, 1);
+            let back_rank = fen.split(['/', ' ']).nth(7).unwrap();
+            assert!(back_ranks.insert(back_rank.to_string()), "{}", fen);
+            let bishops = board.piece_of_type(BISHOP) & board.piece_of_color(WHITE);
+            assert_eq!((bishops & BitBoard(0x55)).num_squares(), 1, "{}", fen);
+            let (king, _, rook, _) = board.castling_squares(WHITE, QueenSide);
+            assert!(rook.file() < king.file());
+            // parsing can't tell Chess960 from standard chess, e.g. position 518, so mark it
+            let mut parsed = board_from_fen(&fen).unwrap();
+            parsed.set_chess960(true);
+            assert_eq!(parsed, board);
+        }
+    }
+
+    #[test]
+    pub fn color_flipped() {
+        let board =
+            board_from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1").unwrap();
+        let flipped = board.color_flipped();
+        assert_eq!(
+            to_string(&flipped, 1),
+            "rnbqkbnr/pppp1ppp/8/4p3/8/8/PPPPPPPP/RNBQKBNR w KQkq e6 0 1"
+        );
+        assert_eq!(flipped.color_flipped(), board);
+
+        let board = board_from_fen("4k2r/8/8/8/8/8/8/R3K3 w Qk - 3 1").unwrap();
+        assert_eq!(
+            to_string(&board.color_flipped(), 1),
+            "r3k3/8/8/8/8/8/8/4K2R b Kq - 3 1"
+        );
+    }
+
+    #[test]
+    pub fn attack_maps() {
+        let board = board_from_fen("4k3/8/8/b7/8/2N5/4R3/r3K2r w - - 0 1").unwrap();
+        let occupancy = board.occupancy();
+
+        assert_eq!(board.checkers(), BitBoard::from_square(SQ_A1) | SQ_H1);
+        assert_eq!(
+            board.attackers_to(SQ_E1, occupancy),
+            BitBoard::from_square(SQ_A1) | SQ_H1 | SQ_E2
+        );
+        assert_eq!(board.pinned(WHITE), BitBoard::from_square(SQ_C3));
+        assert_eq!(board.pinners(WHITE), BitBoard::from_square(SQ_A5));
+        assert_eq!(board.pinned(BLACK), BitBoard::EMPTY);
+        assert!(board.attacks_by(WHITE) * SQ_E8);
+        assert!(!(board.attacks_by(WHITE) * SQ_D8));
+    }
+
+    #[test]
+    pub fn checkers_match_under_check() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        for _i in 0..500 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                let us = board.side_to_play;
+                assert_eq!(!board.checkers().empty(), board.under_check(us));
+                let king = BitBoard::from_square(board.king_pos(us.opposite()));
+                assert_eq!(
+                    board.attacks_by(us) * king,
+                    board.under_check(us.opposite())
+                );
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+    }
 }
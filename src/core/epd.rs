@@ -0,0 +1,157 @@
+use crate::core::board::Board;
+use crate::core::fen::FenComponents;
+use crate::core::r#move::Move;
+
+/// A position in Extended Position Description: the first four FEN fields followed by
+/// operations such as `bm Qxf7+; id "WAC.001";`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Epd {
+    pub board: Board,
+    /// Opcodes with their operands, in order. Quotes around string operands are removed.
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+impl Epd {
+    pub fn operands(&self, opcode: &str) -> Option<&[String]> {
+        self.operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.operands("id")?.first().map(String::as_str)
+    }
+
+    /// The moves of the `bm` operation, or `None` if one of them isn't legal.
+    pub fn best_moves(&self) -> Option<Vec<Move>> {
+        self.moves("bm")
+    }
+
+    /// The moves of the `am` operation, or `None` if one of them isn't legal.
+    pub fn avoid_moves(&self) -> Option<Vec<Move>> {
+        self.moves("am")
+    }
+
+    fn moves(&self, opcode: &str) -> Option<Vec<Move>> {
+        self.operands(opcode)
+            .unwrap_or_default()
+            .iter()
+            .map(|san| Move::from_san(san, &self.board))
+            .collect()
+    }
+}
+
+pub fn epd_from_str(line: &str) -> Option<Epd> {
+    let line = line.trim();
+    let mut fields = line.splitn(5, ' ');
+    let position: Vec<&str> = fields.by_ref().take(4).collect();
+    if position.len() != 4 {
+        return None;
+    }
+    let operations = parse_operations(fields.next().unwrap_or(""))?;
+
+    let operand = |opcode: &str| {
+        operations
+            .iter()
+            .find(|(op, _)| op == opcode)
+            .and_then(|(_, operands)| operands.first().cloned())
+    };
+    let half_move_clock = operand("hmvc").unwrap_or_else(|| String::from("0"));
+    let full_move_number = operand("fmvn").unwrap_or_else(|| String::from("1"));
+    let fen = format!(
+        "{} {} {}",
+        position.join(" "),
+        half_move_clock,
+        full_move_number
+    );
+    let board = FenComponents::parse(&fen).ok()?.board().ok()?;
+    Some(Epd { board, operations })
+}
+
+fn parse_operations(s: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let mut operations = Vec::new();
+    let mut chars = s.chars().peekable();
+    loop {
+        while chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            return Some(operations);
+        }
+        let mut opcode = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_whitespace() || c == ';' {
+                break;
+            }
+            opcode.push(c);
+            chars.next();
+        }
+        let mut operands = Vec::new();
+        loop {
+            match chars.next() {
+                None => return None,
+                Some(';') => break,
+                Some(c) if c.is_whitespace() => {}
+                Some('"') => {
+                    let mut operand = String::new();
+                    loop {
+                        match chars.next()? {
+                            '"' => break,
+                            c => operand.push(c),
+                        }
+                    }
+                    operands.push(operand);
+                }
+                Some(c) => {
+                    let mut operand = String::from(c);
+                    while let Some(&c) = chars.peek() {
+                        if c.is_whitespace() || c == ';' {
+                            break;
+                        }
+                        operand.push(c);
+                        chars.next();
+                    }
+                    operands.push(operand);
+                }
+            }
+        }
+        operations.push((opcode, operands));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::epd::epd_from_str;
+    use crate::core::fen::to_string;
+    use crate::core::r#move::Move;
+    use crate::core::square_constants::*;
+    use crate::core::Piece::*;
+
+    #[test]
+    pub fn parse_epd() {
+        let line =
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+        let epd = epd_from_str(line).unwrap();
+        assert_eq!(
+            to_string(&epd.board, 1),
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1"
+        );
+        assert_eq!(epd.id(), Some("WAC.001"));
+        assert_eq!(epd.best_moves(), Some(vec![Move::new(QUEEN, SQ_G3, SQ_G6)]));
+        assert_eq!(epd.avoid_moves(), Some(vec![]));
+
+        let line = "4k3/8/8/8/8/8/8/4K2R w K - am O-O Kf1; hmvc 12; c0 \"two words\";";
+        let epd = epd_from_str(line).unwrap();
+        assert_eq!(epd.avoid_moves().unwrap().len(), 2);
+        assert_eq!(epd.board.half_move_counter, 12);
+        assert_eq!(epd.operands("c0"), Some(&[String::from("two words")][..]));
+
+        assert!(epd_from_str("4k3/8/8/8/8/8/8/4K3 w -").is_none());
+        assert!(epd_from_str("4k3/8/8/8/8/8/8/4K3 w - - bm Ke2").is_none());
+        assert!(epd_from_str("4k3/8/8/8/8/8/8/4K3 w - - bm Ke3;")
+            .unwrap()
+            .best_moves()
+            .is_none());
+    }
+}
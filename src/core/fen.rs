@@ -32,24 +32,66 @@ pub fn side_to_play(ch: &str) -> Result<Color, ()> {
     }
 }
 
-pub fn castling_rights(s: &str) -> Result<[[bool; 2]; 2], ()> {
-    let mut ans = [[false; 2]; 2];
+/// Sets the castling rights of `board` from a castling field in standard notation (`KQkq`),
+/// X-FEN or Shredder-FEN (`HAha`), where a file letter names the castling rook. `K` and `Q`
+/// refer to the outermost rook on their side of the king. The pieces must already be on the
+/// board. Rights that can't occur in standard chess switch the board to Chess960.
+pub fn castling_rights(s: &str, board: &mut Board) -> Result<(), ()> {
+    board.can_castle_king_side = [false; 2];
+    board.can_castle_queen_side = [false; 2];
     if s == "-" {
-        return Ok(ans);
+        return Ok(());
     }
-    if s.chars().count() > 4 {
+    if s.is_empty() || s.chars().count() > 4 {
         return Err(());
     }
     for ch in s.chars() {
-        match ch {
-            'K' => ans[0][0] = true,
-            'k' => ans[0][1] = true,
-            'Q' => ans[1][0] = true,
-            'q' => ans[1][1] = true,
+        let color = char_to_color(ch);
+        let king = board.king_pos[color];
+        let rank = match color {
+            WHITE => 0,
+            BLACK => 7,
+        };
+        if king.rank() != rank {
+            return Err(());
+        }
+        let rooks = board.piece_of_color(color) & board.piece_of_type(ROOK);
+        let is_rook = |file: &u8| rooks * Square::from_coords(*file, rank);
+        let file = match ch.to_ascii_lowercase() {
+            'k' => (king.file() + 1..8).rev().find(is_rook).ok_or(())?,
+            'q' => (0..king.file()).find(is_rook).ok_or(())?,
+            'a'..='h' => {
+                let file = ch.to_ascii_lowercase() as u8 - b'a';
+                if !is_rook(&file) {
+                    return Err(());
+                }
+                board.chess960 = true;
+                file
+            }
             _ => return Err(()),
+        };
+        let side = if file > king.file() {
+            KingSide
+        } else {
+            QueenSide
+        };
+        if board.can_castle(color, side) {
+            return Err(());
+        }
+        match side {
+            KingSide => board.can_castle_king_side[color] = true,
+            QueenSide => board.can_castle_queen_side[color] = true,
+        }
+        board.castling_rook_file[side][color] = file;
+        let standard_file = match side {
+            KingSide => 7,
+            QueenSide => 0,
+        };
+        if king.file() != 4 || file != standard_file {
+            board.chess960 = true;
         }
     }
-    Ok(ans)
+    Ok(())
 }
 
 pub fn en_passant_square(s: &String) -> Result<Option<Square>, ()> {
@@ -73,7 +115,19 @@ pub fn en_passant_square(s: &String) -> Result<Option<Square>, ()> {
     Ok(Some(Square::from_coords(file, rank)))
 }
 
+/// Writes `board` as FEN. Chess960 castling rights are written as X-FEN: `K` and `Q` when the
+/// castling rook is the outermost one, the rook's file otherwise.
 pub fn to_string(board: &Board, full_move_counter: i32) -> String {
+    to_string_with_castling(board, full_move_counter, false)
+}
+
+/// Writes `board` as Shredder-FEN, where castling rights always name the rook's file, e.g.
+/// `HAha` for the standard start position.
+pub fn to_shredder_string(board: &Board, full_move_counter: i32) -> String {
+    to_string_with_castling(board, full_move_counter, true)
+}
+
+fn to_string_with_castling(board: &Board, full_move_counter: i32, shredder: bool) -> String {
     let mut s: String = String::new();
     for rank in (0..8).rev() {
         let mut counter = 0;
@@ -109,7 +163,7 @@ pub fn to_string(board: &Board, full_move_counter: i32) -> String {
         } else {
             "b"
         },
-        castling_rights_to_string(board),
+        castling_rights_to_string(board, shredder),
         match board.en_passant {
             None => "-".to_string(),
             Some(square) => square.to_string(),
@@ -119,26 +173,36 @@ pub fn to_string(board: &Board, full_move_counter: i32) -> String {
     )
 }
 
-fn castling_rights_to_string(board: &Board) -> String {
+fn castling_rights_to_string(board: &Board, shredder: bool) -> String {
     let mut s = String::with_capacity(4);
-    let mut can_castle = false;
-    if board.can_castle_king_side[WHITE as usize] {
-        s += "K";
-        can_castle = true;
-    }
-    if board.can_castle_queen_side[WHITE as usize] {
-        s += "Q";
-        can_castle = true;
-    }
-    if board.can_castle_king_side[BLACK as usize] {
-        s += "k";
-        can_castle = true;
-    }
-    if board.can_castle_queen_side[BLACK as usize] {
-        s += "q";
-        can_castle = true;
+    for color in [WHITE, BLACK] {
+        for side in [KingSide, QueenSide] {
+            if !board.can_castle(color, side) {
+                continue;
+            }
+            let rook = board.castling_rook_square(color, side);
+            let rooks = board.piece_of_color(color) & board.piece_of_type(ROOK);
+            let outer_rooks = (0..8)
+                .filter(|&file| match side {
+                    KingSide => file > rook.file(),
+                    QueenSide => file < rook.file(),
+                })
+                .any(|file| rooks * Square::from_coords(file, rook.rank()));
+            let ch = if shredder || (board.chess960 && outer_rooks) {
+                (b'a' + rook.file()) as char
+            } else {
+                match side {
+                    KingSide => 'k',
+                    QueenSide => 'q',
+                }
+            };
+            s.push(match color {
+                WHITE => ch.to_ascii_uppercase(),
+                BLACK => ch,
+            });
+        }
     }
-    if !can_castle {
+    if s.is_empty() {
         s += "-"
     }
     s
@@ -187,8 +251,7 @@ impl FenComponents {
         let mut board = Board::empty(SQ_E1, SQ_E8);
         self.set_pieces(&mut board)?;
         board.side_to_play = side_to_play(self.side_to_move.to_ascii_lowercase().as_str())?;
-        [board.can_castle_king_side, board.can_castle_queen_side] =
-            castling_rights(self.castling.as_str())?;
+        castling_rights(self.castling.as_str(), &mut board)?;
         board.en_passant = en_passant_square(&self.en_passant)?;
         board.half_move_counter = self.half_move_clock.parse::<u8>().map_err(|_| ())?;
         Ok(board)
@@ -197,6 +260,7 @@ impl FenComponents {
     fn set_pieces(&self, board: &mut Board) -> Result<(), ()> {
         let mut rank = 7 as usize;
         let mut file = 0 as usize;
+        let mut pieces = Vec::with_capacity(32);
         for ch in self.pieces.chars() {
             if ch == '/' {
                 rank -= 1;
@@ -216,10 +280,14 @@ impl FenComponents {
             if piece == KING {
                 board.set_king_pos(color, sq)?;
             } else {
-                board.put_piece_safe(piece, color, sq)?;
+                pieces.push((piece, color, sq));
             }
             file += 1;
         }
+        // the kings go first, the empty board keeps them on e1 and e8 until then
+        for (piece, color, sq) in pieces {
+            board.put_piece_safe(piece, color, sq)?;
+        }
         Ok(())
     }
 }
@@ -291,4 +359,36 @@ mod tests {
         assert_eq!(to_string(&board, 2), fen);
         assert_eq!(board, board_from_fen(fen).unwrap());
     }
+
+    #[test]
+    pub fn test_chess960_castling_rights() {
+        let board = board_from_fen("rk2r3/8/8/8/8/8/8/RK2R3 w KQkq - 0 1").unwrap();
+        assert!(board.chess960());
+        assert_eq!(board.castling_rook_square(WHITE, KingSide), SQ_E1);
+        assert_eq!(board.castling_rook_square(BLACK, QueenSide), SQ_A8);
+        assert_eq!(
+            to_shredder_string(&board, 1),
+            "rk2r3/8/8/8/8/8/8/RK2R3 w EAea - 0 1"
+        );
+
+        // X-FEN names the rook when it isn't the outermost one
+        let fen = "1r1kr2r/8/8/8/8/8/8/1R1KR2R w EBhb - 0 1";
+        let board = board_from_fen(fen).unwrap();
+        assert_eq!(board.castling_rook_square(WHITE, KingSide), SQ_E1);
+        assert_eq!(board.castling_rook_square(BLACK, KingSide), SQ_H8);
+        assert_eq!(
+            to_string(&board, 1),
+            "1r1kr2r/8/8/8/8/8/8/1R1KR2R w EQkq - 0 1"
+        );
+        assert_eq!(to_shredder_string(&board, 1), fen);
+        assert_eq!(board_from_fen(&to_string(&board, 1)), Some(board));
+
+        // Shredder-FEN of the standard position
+        let board = board_from_fen(&to_shredder_string(&Board::from_initial_position(), 1));
+        assert_eq!(board.unwrap().castling_rook_square(WHITE, KingSide), SQ_H1);
+
+        assert!(board_from_fen("4k3/8/8/8/8/8/8/4K3 w K - 0 1").is_none());
+        assert!(board_from_fen("4k3/8/8/8/8/8/8/4K2R w KH - 0 1").is_none());
+        assert!(board_from_fen("4k3/8/8/8/8/8/8/4K2R w G - 0 1").is_none());
+    }
 }
@@ -1,17 +1,25 @@
+use self::CastlingSide::*;
 use self::Color::*;
 use crate::core::Piece::*;
 use std::fmt::{Display, Formatter, Write};
 use std::ops::{Index, IndexMut};
 
 pub mod bitboard;
-mod bitboard_attacks;
+pub mod bitboard_attacks;
 pub mod bitboard_constants;
 pub mod board;
+pub mod epd;
 pub mod fen;
 pub mod magic_bitboard;
 mod magic_bitboard_magic_numbers;
 pub mod r#move;
 pub mod move_generator;
+pub mod packed_board;
+pub mod retro;
+pub mod san;
+pub mod see;
+#[cfg(feature = "serde")]
+pub mod serialization;
 pub mod square;
 pub mod square_constants;
 pub mod zobrist_hash;
@@ -25,7 +33,7 @@ pub const DOWN_LEFT: i8 = DOWN + LEFT;
 pub const UP_RIGHT: i8 = UP + RIGHT;
 pub const DOWN_RIGHT: i8 = DOWN + RIGHT;
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
 #[repr(u8)]
 pub enum Piece {
     PAWN,
@@ -63,7 +71,7 @@ impl Display for Piece {
     }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone, Copy)]
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
 #[repr(u8)]
 pub enum Color {
     WHITE,
@@ -101,6 +109,29 @@ impl Into<usize> for Color {
     }
 }
 
+#[derive(Eq, PartialEq, Debug, Clone, Copy, Hash)]
+#[repr(u8)]
+pub enum CastlingSide {
+    KingSide,
+    QueenSide,
+}
+
+impl<T> Index<CastlingSide> for [T; 2] {
+    type Output = T;
+
+    #[inline(always)]
+    fn index(&self, index: CastlingSide) -> &Self::Output {
+        &self[index as usize]
+    }
+}
+
+impl<T> IndexMut<CastlingSide> for [T; 2] {
+    #[inline(always)]
+    fn index_mut(&mut self, index: CastlingSide) -> &mut Self::Output {
+        &mut self[index as usize]
+    }
+}
+
 impl<T> Index<Piece> for [T; 6] {
     type Output = T;
 
@@ -1,9 +1,19 @@
 use super::square::Square;
-use crate::core::Piece;
+use crate::core::bitboard::BitBoard;
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::square_constants::*;
+use crate::core::CastlingSide::*;
+use crate::core::Color::*;
+use crate::core::Piece::*;
+use crate::core::{CastlingSide, Color, Piece};
 use std::fmt::{Display, Formatter};
 use Move::*;
 
-#[derive(Clone, Copy, Debug)]
+/// A move of a position. With the `serde` feature, it serializes to UCI notation, which doesn't
+/// say which piece moves, so there is no `Deserialize`: it is read back against the board it is
+/// played on with `serialization::MoveSeed`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Move {
     NormalMove {
         origin: Square,
@@ -50,6 +60,47 @@ impl Move {
             capture,
         }
     }
+
+    pub const fn castle(color: Color, side: CastlingSide) -> Move {
+        match (color, side) {
+            (WHITE, KingSide) => CastleKingSideWhite,
+            (WHITE, QueenSide) => CastleQueenSideWhite,
+            (BLACK, KingSide) => CastleKingSideBlack,
+            (BLACK, QueenSide) => CastleQueenSideBlack,
+        }
+    }
+
+    pub const fn castling_side(self) -> Option<CastlingSide> {
+        match self {
+            CastleKingSideWhite | CastleKingSideBlack => Some(KingSide),
+            CastleQueenSideWhite | CastleQueenSideBlack => Some(QueenSide),
+            _ => None,
+        }
+    }
+
+    /// Writes the move in coordinate notation. On a Chess960 board castling is written as the
+    /// king capturing its own rook, e.g. `b1a1`, as the king's destination may be ambiguous.
+    pub fn to_uci(self, board: &Board) -> String {
+        match self.castling_side() {
+            Some(side) if board.chess960() => {
+                let (king_origin, _, rook_origin, _) =
+                    board.castling_squares(board.side_to_play, side);
+                format!("{}{}", king_origin, rook_origin)
+            }
+            _ => self.to_string(),
+        }
+    }
+
+    /// Finds the legal move of `board` written as `uci` in coordinate notation, e.g. `e7e8q`.
+    pub fn from_uci(uci: &str, board: &Board) -> Option<Move> {
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        generator
+            .moves
+            .iter()
+            .find(|m| m.to_uci(board) == uci)
+            .copied()
+    }
 }
 impl Display for Move {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -70,13 +121,164 @@ impl Display for Move {
     }
 }
 
+/// A move packed in 16 bits: origin in bits 0-5, destination in bits 6-11 and a flag in bits
+/// 12-15. Castling is stored as the king's move in standard chess, also in Chess960. The moving
+/// piece isn't stored, so unpacking needs the board the move is played on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PackedMove(pub u16);
+
+impl PackedMove {
+    pub const NULL: PackedMove = PackedMove(0);
+
+    const NORMAL: u16 = 0;
+    const EN_PASSANT: u16 = 1;
+    const CASTLE: u16 = 2;
+    // promotions use PROMOTION + piece - KNIGHT
+    const PROMOTION: u16 = 4;
+
+    const fn new(origin: Square, dest: Square, flag: u16) -> PackedMove {
+        PackedMove(origin.0 as u16 | (dest.0 as u16) << 6 | flag << 12)
+    }
+
+    pub const fn origin(self) -> Square {
+        Square((self.0 & 0x3F) as u8)
+    }
+
+    pub const fn dest(self) -> Square {
+        Square(((self.0 >> 6) & 0x3F) as u8)
+    }
+
+    const fn flag(self) -> u16 {
+        self.0 >> 12
+    }
+
+    /// Restores the full move, or returns `None` if it can't be a move of `board`'s side to play,
+    /// e.g. when a hash table entry belongs to another position.
+    pub fn unpack(self, board: &Board) -> Option<Move> {
+        if self == PackedMove::NULL {
+            return Some(NullMove);
+        }
+        let origin = self.origin();
+        let dest = self.dest();
+        if self.flag() == PackedMove::CASTLE {
+            let m = match (origin, dest) {
+                (SQ_E1, SQ_G1) => CastleKingSideWhite,
+                (SQ_E1, SQ_C1) => CastleQueenSideWhite,
+                (SQ_E8, SQ_G8) => CastleKingSideBlack,
+                (SQ_E8, SQ_C8) => CastleQueenSideBlack,
+                _ => return None,
+            };
+            let color = if origin == SQ_E1 { WHITE } else { BLACK };
+            let side = m.castling_side()?;
+            return (color == board.side_to_play && board.can_castle(color, side)).then_some(m);
+        }
+        let origin_bb = BitBoard::from_square(origin);
+        if !(board.piece_of_color(board.side_to_play) * origin_bb) {
+            return None;
+        }
+        match self.flag() {
+            PackedMove::NORMAL => Some(Move::new(board.piece_at(origin_bb)?, origin, dest)),
+            PackedMove::EN_PASSANT => Some(Move::en_passant(
+                origin,
+                dest,
+                Square::from_coords(dest.file(), origin.rank()),
+            )),
+            flag if (PackedMove::PROMOTION..=PackedMove::PROMOTION + 3).contains(&flag) => {
+                let piece = Piece::from((flag - PackedMove::PROMOTION) as u8 + KNIGHT as u8);
+                Some(Move::promote(origin, dest, piece))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<Move> for PackedMove {
+    fn from(m: Move) -> Self {
+        match m {
+            NormalMove { origin, dest, .. } => PackedMove::new(origin, dest, PackedMove::NORMAL),
+            EnPassant { origin, dest, .. } => PackedMove::new(origin, dest, PackedMove::EN_PASSANT),
+            Promotion {
+                origin,
+                dest,
+                piece,
+            } => {
+                let flag = PackedMove::PROMOTION + piece as u16 - KNIGHT as u16;
+                PackedMove::new(origin, dest, flag)
+            }
+            CastleKingSideWhite => PackedMove::new(SQ_E1, SQ_G1, PackedMove::CASTLE),
+            CastleQueenSideWhite => PackedMove::new(SQ_E1, SQ_C1, PackedMove::CASTLE),
+            CastleKingSideBlack => PackedMove::new(SQ_E8, SQ_G8, PackedMove::CASTLE),
+            CastleQueenSideBlack => PackedMove::new(SQ_E8, SQ_C8, PackedMove::CASTLE),
+            NullMove => PackedMove::NULL,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::core::r#move::Move;
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::core::r#move::{Move, PackedMove};
+    use crate::core::square_constants::*;
+    use crate::core::Piece::*;
+    use rand::{RngCore, SeedableRng};
     use std::mem::size_of;
 
     #[test]
     pub fn test_move() {
         println!("Size of NormalMove {} ", size_of::<Move>())
     }
+
+    #[test]
+    pub fn test_from_uci() {
+        let board = Board::from_initial_position();
+        assert_eq!(Move::from_uci("e2e4", &board).unwrap().to_string(), "e2e4");
+        assert!(Move::from_uci("e2e5", &board).is_none());
+
+        let board = board_from_fen("4k3/1P6/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            Move::from_uci("e1g1", &board),
+            Some(Move::CastleKingSideWhite)
+        );
+        assert!(matches!(
+            Move::from_uci("b7b8n", &board),
+            Some(Move::Promotion { .. })
+        ));
+
+        let board = board_from_fen("4k3/8/8/8/8/8/8/1RK5 w B - 0 1").unwrap();
+        let m = Move::from_uci("c1b1", &board).unwrap();
+        assert_eq!(m, Move::CastleQueenSideWhite);
+        assert_eq!(m.to_uci(&board), "c1b1");
+        assert!(Move::from_uci("c1c1", &board).is_none());
+    }
+
+    #[test]
+    pub fn test_packed_move_round_trip() {
+        assert_eq!(size_of::<PackedMove>(), 2);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for i in 0..1000 {
+            let mut board = if i % 2 == 0 {
+                Board::from_initial_position()
+            } else {
+                Board::from_chess960_position((rng.next_u64() % 960) as u16)
+            };
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                for &m in mg.moves.iter() {
+                    assert_eq!(PackedMove::from(m).unpack(&board), Some(m));
+                }
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+        let board = Board::from_initial_position();
+        assert_eq!(PackedMove::NULL.unpack(&board), Some(Move::NullMove));
+        let black_move = PackedMove::from(Move::new(PAWN, SQ_E7, SQ_E5));
+        assert_eq!(black_move.unpack(&board), None);
+    }
 }
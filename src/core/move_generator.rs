@@ -5,7 +5,6 @@ use crate::core::board::Board;
 use crate::core::magic_bitboard::*;
 use crate::core::r#move::Move;
 use crate::core::square::Square;
-use crate::core::square_constants::*;
 use crate::core::*;
 
 // Disable warnings
@@ -48,6 +47,8 @@ pub struct MoveGenerator<'a> {
     any_piece: BitBoard,
     block_mask: BitBoard,
     checkers_block_mask: BitBoard,
+    /// Squares the pieces may move to, the enemy pieces when only captures are generated.
+    targets: BitBoard,
     king: BitBoard,
     king_sq: Square,
     other_king: BitBoard,
@@ -56,6 +57,10 @@ pub struct MoveGenerator<'a> {
     bishop_queen: BitBoard,
     us: Color,
     evasive: bool,
+    /// The squares each piece gives check from, every square unless only checks are generated.
+    check_squares: [BitBoard; 6],
+    /// Our pieces standing between one of our sliders and the enemy king.
+    discoverers: BitBoard,
 }
 
 impl MoveGenerator<'_> {
@@ -71,6 +76,7 @@ impl MoveGenerator<'_> {
             any_piece: our_piece | enemy_piece,
             block_mask: BitBoard::EMPTY,
             checkers_block_mask: BitBoard::FULL,
+            targets: BitBoard::FULL,
             king: BitBoard::from_square(board.king_pos[board.side_to_play]),
             king_sq: board.king_pos[board.side_to_play],
             other_king: BitBoard::from_square(board.king_pos[board.side_to_play.opposite()]),
@@ -79,11 +85,17 @@ impl MoveGenerator<'_> {
             bishop_queen: enemy_piece & (board.piece_of_type(BISHOP) | board.piece_of_type(QUEEN)),
             us: board.side_to_play,
             evasive: false,
+            check_squares: [BitBoard::FULL; 6],
+            discoverers: BitBoard::EMPTY,
         }
     }
 
     pub fn generate(&mut self) -> &Vec<Move> {
         self.scan_board();
+        if !self.checkers.empty() {
+            // evasions are always generated in full
+            self.targets = BitBoard::FULL;
+        }
         self.generate_king_moves();
         let num_checkers = self.checkers.num_squares();
         if num_checkers == 2 {
@@ -95,11 +107,53 @@ impl MoveGenerator<'_> {
         } else {
             self.block_mask = BitBoard::FULL;
             self.generate_non_king_moves();
-            self.generate_castles();
+            if self.targets == BitBoard::FULL {
+                self.generate_castles();
+            }
         }
         &self.moves
     }
 
+    /// Generates the captures and promotions for quiescence search, or every evasion when in
+    /// check.
+    pub fn generate_captures(&mut self) -> &Vec<Move> {
+        self.targets = self.enemy_piece;
+        self.generate()
+    }
+
+    /// Generates the moves giving check, e.g. for the attacker of a mate search. Pieces only
+    /// move to the squares they give check from, and off the line to the enemy king when they
+    /// uncover a check along it. Promotions, en passant captures and castling are rare enough
+    /// to be checked one by one.
+    pub fn generate_checks(&mut self) -> &Vec<Move> {
+        let them = self.us.opposite();
+        let enemy_king = self.board.king_pos[them];
+        let rook_squares = rook_attacks(enemy_king, self.any_piece);
+        let bishop_squares = bishop_attacks(enemy_king, self.any_piece);
+        self.check_squares = [
+            pawn_attacks(them, enemy_king),
+            knight_attacks(enemy_king),
+            bishop_squares,
+            rook_squares,
+            rook_squares | bishop_squares,
+            BitBoard::EMPTY,
+        ];
+        let ours = self.board.piece_of_color[self.us];
+        let queens = self.board.piece_of_type(QUEEN);
+        let rooks = ours & (self.board.piece_of_type(ROOK) | queens);
+        let bishops = ours & (self.board.piece_of_type(BISHOP) | queens);
+        self.discoverers = self
+            .update_discoverers(enemy_king, rook_attacks_empty(enemy_king) & rooks)
+            | self.update_discoverers(enemy_king, bishop_attacks_empty(enemy_king) & bishops);
+        self.generate();
+        let board = self.board;
+        self.moves.retain(|&m| match m {
+            Move::NormalMove { .. } => true,
+            _ => board.gives_check(m),
+        });
+        &self.moves
+    }
+
     #[inline(always)]
     fn scan_board(&mut self) {
         let (rook_checkers, bishop_checkers) = self.update_checkers();
@@ -150,10 +204,38 @@ impl MoveGenerator<'_> {
         pinned
     }
 
+    /// Our pieces alone between `sliders` and the enemy king on `enemy_king`.
+    fn update_discoverers(&self, enemy_king: Square, sliders: BitBoard) -> BitBoard {
+        let mut discoverers = BitBoard::EMPTY;
+        let mut remaining = sliders;
+        while !remaining.empty() {
+            let path = LINE_SEGMENT[enemy_king][remaining.pop_lsb()] & self.any_piece;
+            if path.num_squares() == 1 && !(path * self.enemy_piece) {
+                discoverers |= path;
+            }
+        }
+        discoverers
+    }
+
+    /// The squares the `piece` on `origin` may move to when only checks are generated.
+    #[inline(always)]
+    fn check_targets(&self, origin: Square, piece: Piece) -> BitBoard {
+        if self.discoverers * origin {
+            let enemy_king = self.board.king_pos[self.us.opposite()];
+            self.check_squares[piece] | !LINE[origin][enemy_king]
+        } else {
+            self.check_squares[piece]
+        }
+    }
+
     #[inline]
     fn square_attacked(&self, sq: Square) -> bool {
+        self.square_attacked_with(sq, self.any_piece ^ self.king)
+    }
+
+    #[inline]
+    fn square_attacked_with(&self, sq: Square, occupancy: BitBoard) -> bool {
         let enemy = self.enemy_piece;
-        let occupancy = self.any_piece ^ self.king;
         KNIGHT_ATTACKS[sq] * (enemy & self.board.piece_of_type(KNIGHT))
             || PAWN_ATTACKS[self.us][sq] * (enemy & self.board.piece_of_type(PAWN))
             || KING_ATTACKS[sq].intersects(self.other_king)
@@ -163,7 +245,8 @@ impl MoveGenerator<'_> {
 
     #[inline(always)]
     fn generate_king_moves(&mut self) {
-        let mut attacks = king_attacks(self.king_sq) & self.our_piece_i;
+        let mut attacks = king_attacks(self.king_sq) & self.our_piece_i & self.targets;
+        attacks &= self.check_targets(self.king_sq, KING);
         while !attacks.empty() {
             let sq = attacks.pop_lsb();
             if !self.square_attacked(sq) {
@@ -213,32 +296,34 @@ impl MoveGenerator<'_> {
 
     // #[inline(never)]
     fn generate_castles(&mut self) {
-        if self.us == WHITE {
-            if self.board.can_castle_king_side[WHITE] {
-                self.generate_castles_move(BB_F1 | BB_G1, SQ_F1, SQ_G1, Move::CastleKingSideWhite);
-            }
-            if self.board.can_castle_queen_side[WHITE] {
-                let path = BB_D1 | BB_C1 | BB_B1;
-                self.generate_castles_move(path, SQ_D1, SQ_C1, Move::CastleQueenSideWhite);
-            }
-        } else {
-            if self.board.can_castle_king_side[BLACK] {
-                self.generate_castles_move(BB_F8 | BB_G8, SQ_F8, SQ_G8, Move::CastleKingSideBlack);
-            }
-            if self.board.can_castle_queen_side[BLACK] {
-                let path = BB_D8 | BB_C8 | BB_B8;
-                self.generate_castles_move(path, SQ_D8, SQ_C8, Move::CastleQueenSideBlack);
-            }
+        if self.board.can_castle_king_side[self.us] {
+            self.generate_castles_move(KingSide);
+        }
+        if self.board.can_castle_queen_side[self.us] {
+            self.generate_castles_move(QueenSide);
         }
     }
 
+    /// Works for Chess960 too: every square the king or the rook crosses or lands on must be
+    /// empty apart from the two castling pieces, and no square the king crosses or lands on may
+    /// be attacked once the rook has left its square.
     #[inline(always)]
-    fn generate_castles_move(&mut self, path: BitBoard, sq1: Square, sq2: Square, m: Move) {
-        if !self.any_piece.intersects(path) {
-            if !self.square_attacked(sq1) && !self.square_attacked(sq2) {
-                self.moves.push(m);
+    fn generate_castles_move(&mut self, side: CastlingSide) {
+        let (king_origin, king_dest, rook_origin, rook_dest) =
+            self.board.castling_squares(self.us, side);
+        let king_path = LINE_SEGMENT[king_origin][king_dest] | king_dest;
+        let rook_path = LINE_SEGMENT[rook_origin][rook_dest] | rook_dest;
+        let occupancy = (self.any_piece ^ self.king) - rook_origin;
+        if (king_path | rook_path) * occupancy {
+            return;
+        }
+        let mut remaining = king_path;
+        while !remaining.empty() {
+            if self.square_attacked_with(remaining.pop_lsb(), occupancy) {
+                return;
             }
         }
+        self.moves.push(Move::castle(self.us, side));
     }
 
     #[inline]
@@ -246,6 +331,8 @@ impl MoveGenerator<'_> {
         let mut attacks = attacks;
         attacks &= self.our_piece_i;
         attacks &= self.checkers_block_mask;
+        attacks &= self.targets;
+        attacks &= self.check_targets(origin, piece);
         if self.pinned * origin {
             // if piece is pinned, it can only move away from or towards the king, but not any other direction
             attacks &= LINE[origin][self.king_sq];
@@ -261,6 +348,8 @@ impl MoveGenerator<'_> {
 
         attacks &= self.our_piece_i;
         attacks &= self.checkers_block_mask;
+        attacks &= self.targets;
+        attacks &= self.check_targets(origin, KNIGHT);
         while !attacks.empty() {
             let dest = attacks.pop_lsb();
             self.moves.push(Move::new(KNIGHT, origin, dest));
@@ -274,18 +363,24 @@ impl MoveGenerator<'_> {
         let is_promotion = (RANK_1 | RANK_8) * fwd;
         let first_move = !is_promotion && (RANK_2 | RANK_7) * origin_bb;
         let is_not_pinned = !(self.pinned * origin_bb);
+        let check_targets = self.check_targets(origin, PAWN);
         if (!self.any_piece * fwd) && (is_not_pinned || LINE[origin][dest] * (self.king)) {
             // pawn can move forward if
             // 1. there's no piece in the destination square
             // 2. Either:
             //      - it is not pinned
             //      - it is pinned but it is moving towards or away from the king in a line (will continue pinned)
-            if self.block_mask * fwd {
+            if self.block_mask * fwd && (is_promotion || self.targets * fwd && check_targets * fwd)
+            {
                 self.add_pawn_moves(origin, dest, is_promotion);
             }
             if first_move {
                 let fwd2 = fwd.shift(fwd_dir);
-                if !(fwd2 * self.any_piece) && (self.block_mask * fwd2) {
+                if !(fwd2 * self.any_piece)
+                    && (self.block_mask * fwd2)
+                    && (self.targets * fwd2)
+                    && (check_targets * fwd2)
+                {
                     self.moves
                         .push(Move::new(PAWN, origin, dest.shift(fwd_dir)))
                 }
@@ -320,7 +415,8 @@ impl MoveGenerator<'_> {
     fn generate_pawn_captures(&mut self, is_promotion: bool, origin: Square, dest: Square) {
         let dest_bb = BitBoard::from_square(dest);
         if self.enemy_piece * dest_bb {
-            if !self.evasive || self.checkers * dest_bb {
+            let checks = is_promotion || self.check_targets(origin, PAWN) * dest_bb;
+            if (!self.evasive || self.checkers * dest_bb) && checks {
                 self.add_pawn_moves(origin, dest, is_promotion);
             }
         } else if let Some(en_passant) = self.board.en_passant {
@@ -344,12 +440,14 @@ impl MoveGenerator<'_> {
 #[allow(unused_mut)]
 #[cfg(test)]
 mod tests {
+    use crate::core::bitboard::BitBoard;
     use crate::core::board::Board;
     use crate::core::fen::board_from_fen;
     use crate::core::move_generator::MoveGenerator;
     use crate::core::r#move::Move;
     use crate::core::square_constants::*;
     use crate::core::Piece::*;
+    use rand::{RngCore, SeedableRng};
 
     fn performance_test(board: &Board, depth: i32, log: bool) -> usize {
         performance_test_rec(board, depth, log)
@@ -457,6 +555,159 @@ mod tests {
         assert_eq!(performance_test(&b, 5, true), 89941194);
     }
 
+    #[test]
+    pub fn perft_chess960() {
+        let fen = "bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9";
+        let b = board_from_fen(fen).unwrap();
+        assert_eq!(performance_test(&b, 1, true), 21);
+        assert_eq!(performance_test(&b, 2, true), 528);
+        assert_eq!(performance_test(&b, 3, true), 12189);
+        assert_eq!(performance_test(&b, 4, true), 326672);
+        assert_eq!(performance_test(&b, 5, true), 8146062);
+
+        let fen = "2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9";
+        let b = board_from_fen(fen).unwrap();
+        assert_eq!(performance_test(&b, 1, true), 21);
+        assert_eq!(performance_test(&b, 2, true), 807);
+        assert_eq!(performance_test(&b, 3, true), 18002);
+        assert_eq!(performance_test(&b, 4, true), 667366);
+        assert_eq!(performance_test(&b, 5, true), 16253601);
+
+        let fen = "qbbnnrkr/2pp2pp/p7/1p2pp2/8/P3PP2/1PPP1KPP/QBBNNR1R w hf - 0 9";
+        let b = board_from_fen(fen).unwrap();
+        assert_eq!(performance_test(&b, 1, true), 22);
+        assert_eq!(performance_test(&b, 2, true), 593);
+        assert_eq!(performance_test(&b, 3, true), 13440);
+        assert_eq!(performance_test(&b, 4, true), 382958);
+    }
+
+    #[test]
+    pub fn captures_match_filtered_moves() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for _i in 0..500 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                let in_check = !board.checkers().empty();
+                let expected: Vec<Move> = mg
+                    .moves
+                    .iter()
+                    .filter(|m| match **m {
+                        Move::NormalMove { dest, .. } => {
+                            in_check || board.piece_at(BitBoard::from_square(dest)).is_some()
+                        }
+                        Move::EnPassant { .. } | Move::Promotion { .. } => true,
+                        _ => in_check,
+                    })
+                    .copied()
+                    .collect();
+                let mut captures = MoveGenerator::new(&board);
+                captures.generate_captures();
+                assert_eq!(captures.moves, expected, "{}", board);
+
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+    }
+
+    #[test]
+    pub fn checks_match_filtered_moves() {
+        let filtered_checks = |board: &Board| -> Vec<Move> {
+            let mut mg = MoveGenerator::new(board);
+            mg.generate();
+            mg.moves
+                .into_iter()
+                .filter(|&m| {
+                    let mut child = *board;
+                    child.make_move(m);
+                    !child.checkers().empty()
+                })
+                .collect()
+        };
+        // uncovered checks by every piece, pawn pushes, en passant, castling and promotions
+        for fen in [
+            "4k3/8/8/8/4N3/8/8/4R1K1 w - - 0 1",
+            "7k/8/8/8/3R4/8/8/B5K1 w - - 0 1",
+            "4k3/8/8/8/8/8/4K3/4R3 w - - 0 1",
+            "7k/8/8/8/8/2P5/8/B5K1 w - - 0 1",
+            "7k/8/8/3pP3/8/8/8/B3K3 w - d6 0 1",
+            "5k2/8/8/8/8/8/8/4K2R w K - 0 1",
+            "8/2P5/k7/8/8/8/8/4K3 w - - 0 1",
+            "4r1k1/8/8/8/4n3/8/1b6/4K3 b - - 0 1",
+        ] {
+            let board = board_from_fen(fen).unwrap();
+            let mut checks = MoveGenerator::new(&board);
+            checks.generate_checks();
+            assert!(!checks.moves.is_empty(), "{}", board);
+            assert_eq!(checks.moves, filtered_checks(&board), "{}", board);
+        }
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for _i in 0..500 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                let mut checks = MoveGenerator::new(&board);
+                checks.generate_checks();
+                assert_eq!(checks.moves, filtered_checks(&board), "{}", board);
+
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+    }
+
+    #[test]
+    pub fn chess960_castling() {
+        let castles = |fen: &str| {
+            let board = board_from_fen(fen).unwrap();
+            let mut generator = MoveGenerator::new(&board);
+            generator.generate();
+            let mut castles: Vec<Move> = generator
+                .moves
+                .iter()
+                .filter(|m| m.castling_side().is_some())
+                .copied()
+                .collect();
+            castles.sort_by_key(|m| m.to_string());
+            castles
+        };
+        // the king stays on g1 and the rook jumps over it
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/6KR w H - 0 1"),
+            vec![Move::CastleKingSideWhite]
+        );
+        // the king goes from g1 to c1 over the rook
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/4R1K1 w E - 0 1"),
+            vec![Move::CastleQueenSideWhite]
+        );
+        // the rook leaving b1 would expose the king on c1 to the queen
+        assert!(castles("4k3/8/8/8/8/8/8/qRK5 w B - 0 1").is_empty());
+        assert_eq!(
+            castles("4k3/8/8/8/8/8/8/nRK5 w B - 0 1"),
+            vec![Move::CastleQueenSideWhite]
+        );
+        // the rook's destination is occupied by another piece
+        assert!(castles("4k3/8/8/8/8/8/8/1RKN4 w B - 0 1").is_empty());
+        // the rook's destination is attacked, which doesn't matter
+        assert_eq!(
+            castles("3rk3/8/8/8/8/8/8/1RK5 w B - 0 1"),
+            vec![Move::CastleQueenSideWhite]
+        );
+        // a square crossed by the king is attacked
+        assert!(castles("4kr2/8/8/8/8/8/8/1K5R w H - 0 1").is_empty());
+    }
+
     // #[test]
     // pub fn perft_a2a4() {
     //     let mut board = Board::from_initial_position();
@@ -0,0 +1,166 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::board::Board;
+use crate::core::square::Square;
+use crate::core::*;
+
+/// A board packed in 32 bytes, for storing large numbers of positions.
+///
+/// | bytes  | content                                                                      |
+/// |--------|------------------------------------------------------------------------------|
+/// | 0..8   | occupancy bitboard, little endian                                            |
+/// | 8..24  | one nibble per occupied square in increasing square order: `color << 3 \| piece` |
+/// | 24     | bit 0: black to play, bits 1-4: castling rights K, Q, k, q                    |
+/// | 25     | en passant square, or `0xFF` if there is none                                |
+/// | 26     | half move counter                                                            |
+/// | 27     | castling rook files: king side in bits 0-3, queen side in bits 4-7, white     |
+/// | 28     | castling rook files of black, same layout                                    |
+/// | 29     | 1 for Chess960, 0 otherwise                                                  |
+/// | 30..32 | zero                                                                         |
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PackedBoard(pub [u8; 32]);
+
+const NO_EN_PASSANT: u8 = 0xFF;
+
+impl PackedBoard {
+    /// Packs `board`, or returns `None` if it has more than 32 pieces.
+    pub fn pack(board: &Board) -> Option<PackedBoard> {
+        let occupancy = board.occupancy();
+        if occupancy.num_squares() > 32 {
+            return None;
+        }
+        let mut bytes = [0; 32];
+        bytes[0..8].copy_from_slice(&occupancy.0.to_le_bytes());
+
+        let mut remaining = occupancy;
+        let mut i = 0;
+        while !remaining.empty() {
+            let square_bb = BitBoard::from_square(remaining.pop_lsb());
+            let piece = board.piece_at(square_bb)?;
+            let color = board.color_at(square_bb)?;
+            bytes[8 + i / 2] |= ((color as u8) << 3 | piece as u8) << (4 * (i % 2));
+            i += 1;
+        }
+
+        bytes[24] = board.side_to_play as u8
+            | (board.can_castle_king_side[WHITE] as u8) << 1
+            | (board.can_castle_queen_side[WHITE] as u8) << 2
+            | (board.can_castle_king_side[BLACK] as u8) << 3
+            | (board.can_castle_queen_side[BLACK] as u8) << 4;
+        bytes[25] = board.en_passant.map_or(NO_EN_PASSANT, |square| square.0);
+        bytes[26] = board.half_move_counter;
+        for color in [WHITE, BLACK] {
+            bytes[27 + color as usize] = board.castling_rook_file[KingSide][color]
+                | board.castling_rook_file[QueenSide][color] << 4;
+        }
+        bytes[29] = board.chess960 as u8;
+        Some(PackedBoard(bytes))
+    }
+
+    /// Restores the board, or returns `None` if the bytes don't describe a board with one king
+    /// of each color.
+    pub fn unpack(&self) -> Option<Board> {
+        let bytes = &self.0;
+        let mut occupancy = BitBoard(u64::from_le_bytes(bytes[0..8].try_into().unwrap()));
+        let rook_files = [bytes[27], bytes[28]];
+        if occupancy.num_squares() > 32
+            || bytes[24] >> 5 != 0
+            || rook_files.iter().any(|files| files & 0x88 != 0)
+            || bytes[29] > 1
+        {
+            return None;
+        }
+
+        let mut piece_of_color = [BitBoard::EMPTY; 2];
+        let mut piece_of_type = [BitBoard::EMPTY; 5];
+        let mut king_pos: [Option<Square>; 2] = [None, None];
+        let mut i = 0;
+        while !occupancy.empty() {
+            let square = occupancy.pop_lsb();
+            let code = (bytes[8 + i / 2] >> (4 * (i % 2))) & 0xF;
+            let (color, piece) = ((code >> 3) as usize, code & 7);
+            if piece > KING as u8 {
+                return None;
+            }
+            piece_of_color[color] |= BitBoard::from_square(square);
+            if piece == KING as u8 {
+                if king_pos[color].is_some() {
+                    return None;
+                }
+                king_pos[color] = Some(square);
+            } else {
+                piece_of_type[piece as usize] |= BitBoard::from_square(square);
+            }
+            i += 1;
+        }
+
+        let en_passant = match bytes[25] {
+            NO_EN_PASSANT => None,
+            square if square < 64 => Some(Square(square)),
+            _ => return None,
+        };
+        Some(Board {
+            piece_of_color,
+            piece_of_type,
+            king_pos: [king_pos[WHITE]?, king_pos[BLACK]?],
+            en_passant,
+            can_castle_king_side: [bytes[24] & 2 != 0, bytes[24] & 8 != 0],
+            can_castle_queen_side: [bytes[24] & 4 != 0, bytes[24] & 16 != 0],
+            castling_rook_file: [
+                [rook_files[WHITE] & 0xF, rook_files[BLACK] & 0xF],
+                [rook_files[WHITE] >> 4, rook_files[BLACK] >> 4],
+            ],
+            chess960: bytes[29] == 1,
+            side_to_play: if bytes[24] & 1 == 0 { WHITE } else { BLACK },
+            half_move_counter: bytes[26],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::core::packed_board::PackedBoard;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    pub fn test_size() {
+        assert_eq!(std::mem::size_of::<PackedBoard>(), 32);
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for i in 0..1000 {
+            let mut board = if i % 2 == 0 {
+                Board::from_initial_position()
+            } else {
+                Board::from_chess960_position((rng.next_u64() % 960) as u16)
+            };
+            for _j in 0..200 {
+                let packed = PackedBoard::pack(&board).unwrap();
+                assert_eq!(packed.unpack(), Some(board), "{}", board);
+
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_invalid() {
+        let board = board_from_fen("4k3/pppppppp/pppppppp/8/8/PPPPPPPP/PPPPPPPP/4K3 w - - 0 1");
+        assert_eq!(PackedBoard::pack(&board.unwrap()), None);
+
+        let mut packed = PackedBoard::pack(&Board::from_initial_position()).unwrap();
+        // turn the white king into a black king
+        packed.0[8 + 2] |= 8;
+        assert_eq!(packed.unpack(), None);
+    }
+}
@@ -0,0 +1,410 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::bitboard_attacks::*;
+use crate::core::bitboard_constants::*;
+use crate::core::board::Board;
+use crate::core::magic_bitboard::*;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::square::Square;
+use crate::core::*;
+
+/// A move taken back: the move as it was played, and the piece of the other side it captured,
+/// which taking it back puts back on the board. En passant captured a pawn.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UnMove {
+    pub m: Move,
+    pub uncapture: Option<Piece>,
+}
+
+impl UnMove {
+    pub const fn new(m: Move, uncapture: Option<Piece>) -> UnMove {
+        UnMove { m, uncapture }
+    }
+}
+
+/// The pieces a move may have captured, pawns only off the first and last ranks.
+const UNCAPTURES: [Piece; 5] = [PAWN, KNIGHT, BISHOP, ROOK, QUEEN];
+
+/// Generates the moves that could have led to a position: the un-moves of the side that just
+/// played, which is the opposite of the side to play. Captures are taken back with each piece
+/// type that may have been captured, promotions by putting the pawn back, and en passant
+/// captures by putting back the pawn that had just moved two squares. Castling is taken back
+/// in standard chess only.
+///
+/// An un-move is only generated if it leads to a legal position, where the side that just
+/// played could have played the move, which means that the side to play can't be in check
+/// there. When the position has an en passant square, the last move can only be the pawn
+/// moving two squares.
+pub struct UnMoveGenerator<'a> {
+    pub board: &'a Board,
+    pub unmoves: Vec<UnMove>,
+    us: Color,
+    empty: BitBoard,
+}
+
+impl UnMoveGenerator<'_> {
+    pub fn new(board: &Board) -> UnMoveGenerator<'_> {
+        UnMoveGenerator {
+            board,
+            unmoves: Vec::with_capacity(256),
+            us: board.side_to_play.opposite(),
+            empty: !board.occupancy(),
+        }
+    }
+
+    pub fn generate(&mut self) -> &Vec<UnMove> {
+        let board = self.board;
+        if let Some(en_passant) = board.en_passant {
+            let fwd = self.us.fwd_dir();
+            let origin = en_passant.shift(-fwd);
+            let dest = en_passant.shift(fwd);
+            self.add(UnMove::new(Move::new(PAWN, origin, dest), None));
+            return &self.unmoves;
+        }
+        self.generate_king_unmoves();
+        self.generate_piece_unmoves();
+        self.generate_pawn_unmoves();
+        if !board.chess960() {
+            self.generate_uncastles(KingSide);
+            self.generate_uncastles(QueenSide);
+        }
+        &self.unmoves
+    }
+
+    fn generate_king_unmoves(&mut self) {
+        let dest = self.board.king_pos[self.us];
+        let mut origins = king_attacks(dest) & self.empty;
+        while !origins.empty() {
+            let origin = origins.pop_lsb();
+            let m = Move::new(KING, origin, dest);
+            self.add(UnMove::new(m, None));
+            self.add_uncaptures(m);
+        }
+    }
+
+    fn generate_piece_unmoves(&mut self) {
+        let occupancy = self.board.occupancy();
+        let last_rank = match self.us {
+            WHITE => RANK_8,
+            BLACK => RANK_1,
+        };
+        for piece in [KNIGHT, BISHOP, ROOK, QUEEN] {
+            let mut remaining =
+                self.board.piece_of_color[self.us] & self.board.piece_of_type(piece);
+            while !remaining.empty() {
+                let dest = remaining.pop_lsb();
+                let attacks = match piece {
+                    KNIGHT => knight_attacks(dest),
+                    BISHOP => bishop_attacks(dest, occupancy),
+                    ROOK => rook_attacks(dest, occupancy),
+                    _ => rook_attacks(dest, occupancy) | bishop_attacks(dest, occupancy),
+                };
+                let mut origins = attacks & self.empty;
+                while !origins.empty() {
+                    let origin = origins.pop_lsb();
+                    let m = Move::new(piece, origin, dest);
+                    self.add(UnMove::new(m, None));
+                    self.add_uncaptures(m);
+                }
+                if last_rank * dest {
+                    self.generate_unpromotions(dest, piece);
+                }
+            }
+        }
+    }
+
+    /// Turns `piece` on the last rank back into the pawn it was promoted from.
+    fn generate_unpromotions(&mut self, dest: Square, piece: Piece) {
+        let behind = dest.shift(-self.us.fwd_dir());
+        if self.empty * behind {
+            self.add(UnMove::new(Move::promote(behind, dest, piece), None));
+        }
+        let mut origins = pawn_attacks(self.us.opposite(), dest) & self.empty;
+        while !origins.empty() {
+            let origin = origins.pop_lsb();
+            self.add_uncaptures(Move::promote(origin, dest, piece));
+        }
+    }
+
+    fn generate_pawn_unmoves(&mut self) {
+        let fwd = self.us.fwd_dir();
+        let (second_rank, fourth_rank, sixth_rank) = match self.us {
+            WHITE => (RANK_2, RANK_4, RANK_6),
+            BLACK => (RANK_7, RANK_5, RANK_3),
+        };
+        let mut remaining = self.board.piece_of_color[self.us] & self.board.piece_of_type(PAWN);
+        while !remaining.empty() {
+            let dest = remaining.pop_lsb();
+            // a pawn on its second rank never moved
+            if second_rank * dest {
+                continue;
+            }
+            let behind = dest.shift(-fwd);
+            if self.empty * behind {
+                self.add(UnMove::new(Move::new(PAWN, behind, dest), None));
+                let start = behind.shift(-fwd);
+                if fourth_rank * dest && self.empty * start {
+                    self.add(UnMove::new(Move::new(PAWN, start, dest), None));
+                }
+            }
+            let mut origins = pawn_attacks(self.us.opposite(), dest) & self.empty;
+            while !origins.empty() {
+                let origin = origins.pop_lsb();
+                self.add_uncaptures(Move::new(PAWN, origin, dest));
+                // the pawn captured en passant had just moved two squares past `dest`
+                if sixth_rank * dest && self.empty * behind && self.empty * dest.shift(fwd) {
+                    let m = Move::en_passant(origin, dest, behind);
+                    self.add(UnMove::new(m, Some(PAWN)));
+                }
+            }
+        }
+    }
+
+    fn generate_uncastles(&mut self, side: CastlingSide) {
+        let (rook_file, king_file, rook_dest_file) = match side {
+            KingSide => (7, 6, 5),
+            QueenSide => (0, 2, 3),
+        };
+        let rank = match self.us {
+            WHITE => 0,
+            BLACK => 7,
+        };
+        let rooks = self.board.piece_of_color[self.us] & self.board.piece_of_type(ROOK);
+        if self.board.king_pos[self.us] != Square::from_coords(king_file, rank)
+            || !(rooks * Square::from_coords(rook_dest_file, rank))
+            || !(self.empty * Square::from_coords(4, rank))
+            || !(self.empty * Square::from_coords(rook_file, rank))
+        {
+            return;
+        }
+        if side == QueenSide && !(self.empty * Square::from_coords(1, rank)) {
+            return;
+        }
+        let m = Move::castle(self.us, side);
+        let mut previous = *self.board;
+        previous.unmake(UnMove::new(m, None));
+        // the king can't have castled out of or through check
+        let mut generator = MoveGenerator::new(&previous);
+        if generator.generate().contains(&m) {
+            self.add(UnMove::new(m, None));
+        }
+    }
+
+    /// Adds an un-move of `m` capturing each piece that may have been on its destination.
+    fn add_uncaptures(&mut self, m: Move) {
+        let dest = match m {
+            Move::NormalMove { dest, .. } | Move::Promotion { dest, .. } => dest,
+            _ => return,
+        };
+        let back_rank = (RANK_1 | RANK_8) * dest;
+        for piece in UNCAPTURES {
+            if piece != PAWN || !back_rank {
+                self.add(UnMove::new(m, Some(piece)));
+            }
+        }
+    }
+
+    /// Adds `unmove` if the side to play isn't in check once it is taken back.
+    fn add(&mut self, unmove: UnMove) {
+        let mut previous = *self.board;
+        previous.unmake(unmove);
+        if !previous.under_check(self.board.side_to_play) {
+            self.unmoves.push(unmove);
+        }
+    }
+}
+
+impl Board {
+    /// Takes back `unmove`, played by the side not to play, with no state saved when the move
+    /// was made. What the position can't tell is set to the least it may have been: the en
+    /// passant square is only set when taking back an en passant capture, castling rights are
+    /// only given back when taking back castling, and the half move counter is decremented.
+    pub fn unmake(&mut self, unmove: UnMove) {
+        let us = self.side_to_play.opposite();
+        self.side_to_play = us;
+        self.en_passant = None;
+        self.half_move_counter = self.half_move_counter.saturating_sub(1);
+        match unmove.m {
+            Move::NormalMove {
+                origin,
+                dest,
+                piece,
+            } => {
+                let origin_bb = BitBoard::from_square(origin);
+                if piece == KING {
+                    self.set_king_pos_fast(us, origin, origin_bb);
+                } else {
+                    self.remove_piece_fast(!BitBoard::from_square(dest));
+                    self.put_piece_fast(piece, us, origin_bb);
+                }
+                self.put_uncapture(unmove.uncapture, dest);
+            }
+            Move::Promotion { origin, dest, .. } => {
+                self.remove_piece_fast(!BitBoard::from_square(dest));
+                self.put_piece_fast(PAWN, us, BitBoard::from_square(origin));
+                self.put_uncapture(unmove.uncapture, dest);
+            }
+            Move::EnPassant {
+                origin,
+                dest,
+                capture,
+            } => {
+                self.remove_piece_fast(!BitBoard::from_square(dest));
+                self.put_piece_fast(PAWN, us, BitBoard::from_square(origin));
+                self.put_piece_fast(PAWN, us.opposite(), BitBoard::from_square(capture));
+                self.en_passant = Some(dest);
+            }
+            Move::NullMove => {}
+            castle => {
+                let side = castle.castling_side().unwrap();
+                let (_, king_dest, rook_origin, rook_dest) = self.castling_squares(us, side);
+                let king_origin = Square::from_coords(4, king_dest.rank());
+                self.remove_piece_fast(!BitBoard::from_square(rook_dest));
+                self.set_king_pos_fast(us, king_origin, BitBoard::from_square(king_origin));
+                self.put_piece_fast(ROOK, us, BitBoard::from_square(rook_origin));
+                match side {
+                    KingSide => self.can_castle_king_side[us] = true,
+                    QueenSide => self.can_castle_queen_side[us] = true,
+                }
+            }
+        }
+    }
+
+    fn put_uncapture(&mut self, uncapture: Option<Piece>, square: Square) {
+        if let Some(piece) = uncapture {
+            let them = self.side_to_play.opposite();
+            self.put_piece_fast(piece, them, BitBoard::from_square(square));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::PackedMove;
+    use crate::core::retro::*;
+    use crate::core::square_constants::*;
+
+    fn legal_moves(board: &Board) -> Vec<Move> {
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        generator.moves
+    }
+
+    fn unmoves(board: &Board) -> Vec<UnMove> {
+        let mut generator = UnMoveGenerator::new(board);
+        generator.generate();
+        generator.unmoves
+    }
+
+    fn same_position(a: &Board, b: &Board) -> bool {
+        a.piece_of_color == b.piece_of_color
+            && a.piece_of_type == b.piece_of_type
+            && a.king_pos == b.king_pos
+            && a.side_to_play == b.side_to_play
+    }
+
+    const POSITIONS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+    ];
+
+    #[test]
+    pub fn every_move_can_be_taken_back() {
+        for fen in POSITIONS {
+            let mut boards = vec![board_from_fen(fen).unwrap()];
+            for m in legal_moves(&boards[0]) {
+                let mut child = boards[0];
+                child.make_move(m);
+                boards.push(child);
+            }
+            for board in boards {
+                for m in legal_moves(&board) {
+                    let captured = match m {
+                        Move::EnPassant { .. } => Some(PAWN),
+                        Move::NormalMove { dest, .. } | Move::Promotion { dest, .. } => {
+                            board.piece_at(BitBoard::from_square(dest))
+                        }
+                        _ => None,
+                    };
+                    let mut child = board;
+                    child.make_move(m);
+                    let unmove = UnMove::new(m, captured);
+                    assert!(unmoves(&child).contains(&unmove), "{} {}", board, m);
+                    child.unmake(unmove);
+                    assert!(same_position(&child, &board), "{} {}", board, m);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn every_unmove_can_be_played() {
+        for fen in POSITIONS {
+            let board = board_from_fen(fen).unwrap();
+            for m in legal_moves(&board) {
+                let mut child = board;
+                child.make_move(m);
+                for unmove in unmoves(&child) {
+                    let mut previous = child;
+                    previous.unmake(unmove);
+                    assert!(previous.check_consistency());
+                    assert!(
+                        legal_moves(&previous).contains(&unmove.m),
+                        "{} {:?}",
+                        child,
+                        unmove
+                    );
+                    previous.make_move(unmove.m);
+                    assert!(same_position(&previous, &child), "{} {:?}", child, unmove);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn special_unmoves() {
+        let board = board_from_fen("Q1b1k3/8/3P4/8/8/8/8/5RK1 b - - 0 1").unwrap();
+        let found = unmoves(&board);
+        let castle = UnMove::new(Move::CastleKingSideWhite, None);
+        assert!(found.contains(&castle));
+        assert!(found.contains(&UnMove::new(Move::promote(SQ_A7, SQ_A8, QUEEN), None)));
+        let capture = UnMove::new(Move::promote(SQ_B7, SQ_A8, QUEEN), Some(ROOK));
+        assert!(found.contains(&capture));
+        let en_passant = UnMove::new(Move::en_passant(SQ_C5, SQ_D6, SQ_D5), Some(PAWN));
+        assert!(found.contains(&en_passant));
+        // no pawn on the last rank, no king taken
+        assert!(!found.iter().any(|unmove| unmove.uncapture == Some(KING)
+            || (unmove.uncapture == Some(PAWN) && PackedMove::from(unmove.m).dest() == SQ_A8)));
+        // black can't have been in check with white to play
+        assert!(found.contains(&UnMove::new(Move::new(ROOK, SQ_F7, SQ_F1), None)));
+        assert!(!found.contains(&UnMove::new(Move::new(ROOK, SQ_F8, SQ_F1), None)));
+
+        let mut previous = board;
+        previous.unmake(en_passant);
+        assert_eq!(previous.side_to_play, WHITE);
+        assert_eq!(previous.en_passant(), Some(SQ_D6));
+        assert_eq!(previous.piece_at(BitBoard::from_square(SQ_D5)), Some(PAWN));
+        assert_eq!(previous.color_at(BitBoard::from_square(SQ_D5)), Some(BLACK));
+
+        let mut previous = board;
+        previous.unmake(castle);
+        assert_eq!(previous.king_pos(WHITE), SQ_E1);
+        assert!(previous.can_castle(WHITE, KingSide));
+        assert!(!previous.can_castle(WHITE, QueenSide));
+
+        // the rook back on h1 would give check to the king on h8, with white to play
+        let board = board_from_fen("7k/8/8/8/8/8/8/5RK1 b - - 0 1").unwrap();
+        assert!(!unmoves(&board).contains(&castle));
+
+        // only the pawn moving two squares gives an en passant square
+        let board = board_from_fen("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(
+            unmoves(&board),
+            vec![UnMove::new(Move::new(PAWN, SQ_E2, SQ_E4), None)]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::square::Square;
+use crate::core::*;
+
+impl Move {
+    /// Writes the move in standard algebraic notation, e.g. `Nbd7`, `exd6`, `e8=Q+` or `O-O-O#`.
+    pub fn to_san(self, board: &Board) -> String {
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        let mut san = san_without_suffix(self, board, &generator.moves);
+
+        let mut new_board = *board;
+        new_board.make_move(self);
+        if !new_board.checkers().empty() {
+            let mut replies = MoveGenerator::new(&new_board);
+            replies.generate();
+            san.push(if replies.moves.is_empty() { '#' } else { '+' });
+        }
+        san
+    }
+
+    /// Finds the legal move of `board` written as `san` in standard algebraic notation. Check
+    /// and annotation suffixes are optional, and castling may be written with zeros.
+    pub fn from_san(san: &str, board: &Board) -> Option<Move> {
+        let wanted = normalize(san);
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        generator
+            .moves
+            .iter()
+            .find(|&&m| normalize(&san_without_suffix(m, board, &generator.moves)) == wanted)
+            .copied()
+    }
+}
+
+/// Writes a line of moves played from `board` in standard algebraic notation, separated by
+/// spaces.
+pub fn line_to_san(board: &Board, moves: &[Move]) -> String {
+    let mut board = *board;
+    let mut sans = Vec::with_capacity(moves.len());
+    for &m in moves {
+        sans.push(m.to_san(&board));
+        board.make_move(m);
+    }
+    sans.join(" ")
+}
+
+fn normalize(san: &str) -> String {
+    san.trim_end_matches(['+', '#', '!', '?'])
+        .replace('0', "O")
+        .replace('=', "")
+}
+
+fn san_without_suffix(m: Move, board: &Board, legal_moves: &[Move]) -> String {
+    let (origin, dest, piece, promotion) = match m {
+        Move::NormalMove {
+            origin,
+            dest,
+            piece,
+        } => (origin, dest, piece, None),
+        Move::Promotion {
+            origin,
+            dest,
+            piece,
+        } => (origin, dest, PAWN, Some(piece)),
+        Move::EnPassant { origin, dest, .. } => (origin, dest, PAWN, None),
+        Move::CastleKingSideWhite | Move::CastleKingSideBlack => return String::from("O-O"),
+        Move::CastleQueenSideWhite | Move::CastleQueenSideBlack => return String::from("O-O-O"),
+        Move::NullMove => return String::from("--"),
+    };
+    let is_capture = matches!(m, Move::EnPassant { .. })
+        || board.piece_of_opposite_color(board.side_to_play) * BitBoard::from_square(dest);
+
+    let mut san = String::with_capacity(7);
+    if piece == PAWN {
+        if is_capture {
+            san.push(file_char(origin));
+        }
+    } else {
+        san.push(
+            piece
+                .to_string()
+                .to_ascii_uppercase()
+                .chars()
+                .next()
+                .unwrap(),
+        );
+        let rivals: Vec<Square> = legal_moves
+            .iter()
+            .filter_map(|&other| match other {
+                Move::NormalMove {
+                    origin: other_origin,
+                    dest: other_dest,
+                    piece: other_piece,
+                } if other_piece == piece && other_dest == dest && other_origin != origin => {
+                    Some(other_origin)
+                }
+                _ => None,
+            })
+            .collect();
+        if !rivals.is_empty() {
+            if rivals.iter().all(|rival| rival.file() != origin.file()) {
+                san.push(file_char(origin));
+            } else if rivals.iter().all(|rival| rival.rank() != origin.rank()) {
+                san.push(rank_char(origin));
+            } else {
+                san.push(file_char(origin));
+                san.push(rank_char(origin));
+            }
+        }
+    }
+    if is_capture {
+        san.push('x');
+    }
+    san += &dest.to_string();
+    if let Some(promotion) = promotion {
+        san.push('=');
+        san.push(
+            promotion
+                .to_string()
+                .to_ascii_uppercase()
+                .chars()
+                .next()
+                .unwrap(),
+        );
+    }
+    san
+}
+
+fn file_char(square: Square) -> char {
+    (b'a' + square.file()) as char
+}
+
+fn rank_char(square: Square) -> char {
+    (b'1' + square.rank()) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::core::r#move::Move;
+    use crate::core::san::line_to_san;
+    use crate::core::square_constants::*;
+    use crate::core::Piece::*;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    pub fn san_notation() {
+        let board = Board::from_initial_position();
+        assert_eq!(Move::new(KNIGHT, SQ_G1, SQ_F3).to_san(&board), "Nf3");
+        assert_eq!(Move::new(PAWN, SQ_E2, SQ_E4).to_san(&board), "e4");
+
+        // knights on b1 and f3 can both reach d2, rooks on a1 and a5 both reach a3
+        let board = board_from_fen("4k3/8/8/R7/8/5N2/8/RN2K3 w - - 0 1").unwrap();
+        assert_eq!(Move::new(KNIGHT, SQ_B1, SQ_D2).to_san(&board), "Nbd2");
+        assert_eq!(Move::new(ROOK, SQ_A1, SQ_A3).to_san(&board), "R1a3");
+
+        let board = board_from_fen("1r2k3/P7/8/3pP3/8/8/8/R3K2R w KQ d6 0 1").unwrap();
+        assert_eq!(Move::en_passant(SQ_E5, SQ_D6, SQ_D5).to_san(&board), "exd6");
+        assert_eq!(Move::promote(SQ_A7, SQ_B8, QUEEN).to_san(&board), "axb8=Q+");
+        assert_eq!(Move::CastleQueenSideWhite.to_san(&board), "O-O-O");
+        assert_eq!(Move::new(ROOK, SQ_H1, SQ_H8).to_san(&board), "Rh8+");
+
+        let board = board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        assert_eq!(Move::new(ROOK, SQ_A1, SQ_A8).to_san(&board), "Ra8#");
+        assert_eq!(
+            line_to_san(
+                &board,
+                &[Move::new(ROOK, SQ_A1, SQ_A7), Move::new(PAWN, SQ_H7, SQ_H6)]
+            ),
+            "Ra7 h6"
+        );
+    }
+
+    #[test]
+    pub fn san_round_trip() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for _i in 0..100 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..200 {
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                for &m in mg.moves.iter() {
+                    assert_eq!(Move::from_san(&m.to_san(&board), &board), Some(m));
+                }
+                let m = mg.moves[rng.next_u64() as usize % mg.moves.len()];
+                board.make_move(m);
+            }
+        }
+        let board = board_from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert_eq!(
+            Move::from_san("0-0+", &board),
+            Some(Move::CastleKingSideWhite)
+        );
+        assert_eq!(Move::from_san("Rh9", &board), None);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::bitboard_constants::*;
+use crate::core::board::Board;
+use crate::core::magic_bitboard::*;
+use crate::core::r#move::Move;
+use crate::core::square::Square;
+use crate::core::*;
+
+/// Piece values used by the static exchange evaluation, indexed by `Piece`.
+pub const SEE_VALUE: [i32; 6] = [100, 325, 325, 500, 1000, 20000];
+
+impl Board {
+    /// Static exchange evaluation: the material balance for the side to play after `m` and the
+    /// best sequence of recaptures on the destination square, always capturing with the least
+    /// valuable attacker. Pins are not taken into account.
+    pub fn see(&self, m: Move) -> i32 {
+        let (origin, dest, moved, captured) = match m {
+            Move::NormalMove {
+                origin,
+                dest,
+                piece,
+            }
+            | Move::Promotion {
+                origin,
+                dest,
+                piece,
+            } => (origin, dest, piece, self.piece_at(BitBoard::from(dest))),
+            Move::EnPassant { origin, dest, .. } => (origin, dest, PAWN, Some(PAWN)),
+            _ => return 0,
+        };
+
+        let mut occupancy = self.occupancy() - origin;
+        if let Move::EnPassant { capture, .. } = m {
+            occupancy -= capture;
+        }
+
+        let mut gain = [0; 32];
+        gain[0] = captured.map_or(0, |piece| SEE_VALUE[piece]);
+        if let Move::Promotion { piece, .. } = m {
+            gain[0] += SEE_VALUE[piece] - SEE_VALUE[PAWN];
+        }
+        let mut on_square = SEE_VALUE[moved];
+
+        let rook_queen = self.piece_of_type(ROOK) | self.piece_of_type(QUEEN);
+        let bishop_queen = self.piece_of_type(BISHOP) | self.piece_of_type(QUEEN);
+        let promotion_rank = BitBoard::from_square(dest) * (RANK_1 | RANK_8);
+        let mut attackers = self.attackers_to(dest, occupancy) & occupancy;
+        let mut side = self.side_to_play.opposite();
+        let mut depth = 0;
+
+        loop {
+            let our_attackers = attackers & self.piece_of_color(side);
+            if our_attackers.empty() {
+                break;
+            }
+            let (attacker, piece) = self.least_valuable_attacker(our_attackers);
+            if piece == KING && attackers * self.piece_of_color(side.opposite()) {
+                // the king can't recapture into a square that is still defended
+                break;
+            }
+
+            depth += 1;
+            gain[depth] = on_square - gain[depth - 1];
+            on_square = SEE_VALUE[piece];
+            if piece == PAWN && promotion_rank {
+                gain[depth] += SEE_VALUE[QUEEN] - SEE_VALUE[PAWN];
+                on_square = SEE_VALUE[QUEEN];
+            }
+
+            occupancy -= attacker;
+            // removing a piece may reveal a slider standing behind it
+            if piece == PAWN || piece == BISHOP || piece == QUEEN {
+                attackers |= bishop_attacks(dest, occupancy) & bishop_queen;
+            }
+            if piece == ROOK || piece == QUEEN {
+                attackers |= rook_attacks(dest, occupancy) & rook_queen;
+            }
+            attackers &= occupancy;
+            side = side.opposite();
+        }
+
+        while depth > 0 {
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+            depth -= 1;
+        }
+        gain[0]
+    }
+
+    /// Returns whether the static exchange evaluation of `m` is at least `threshold`.
+    pub fn see_ge(&self, m: Move, threshold: i32) -> bool {
+        self.see(m) >= threshold
+    }
+
+    fn least_valuable_attacker(&self, attackers: BitBoard) -> (Square, Piece) {
+        for piece in [PAWN, KNIGHT, BISHOP, ROOK, QUEEN] {
+            let mut candidates = attackers & self.piece_of_type(piece);
+            if !candidates.empty() {
+                return (candidates.pop_lsb(), piece);
+            }
+        }
+        let mut king = attackers;
+        (king.pop_lsb(), KING)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::core::square_constants::*;
+    use crate::core::Piece::*;
+
+    #[test]
+    pub fn see_undefended_pawn() {
+        let board = board_from_fen("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::new(ROOK, SQ_E1, SQ_E5)), 100);
+    }
+
+    #[test]
+    pub fn see_with_x_rays() {
+        let fen = "1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1";
+        let board = board_from_fen(fen).unwrap();
+        let m = Move::new(KNIGHT, SQ_D3, SQ_E5);
+        assert_eq!(board.see(m), -225);
+        assert!(board.see_ge(m, -225));
+        assert!(!board.see_ge(m, 0));
+    }
+
+    #[test]
+    pub fn see_quiet_move_to_attacked_square() {
+        let board = board_from_fen("4k3/8/3p4/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::new(KNIGHT, SQ_E4, SQ_C5)), -325);
+        assert_eq!(board.see(Move::new(KNIGHT, SQ_E4, SQ_F6)), 0);
+    }
+
+    #[test]
+    pub fn see_en_passant() {
+        let board = board_from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(board.see(Move::en_passant(SQ_E5, SQ_D6, SQ_D5)), 100);
+        let board = board_from_fen("4k3/2p5/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(board.see(Move::en_passant(SQ_E5, SQ_D6, SQ_D5)), 0);
+    }
+
+    #[test]
+    pub fn see_promotions() {
+        let board = board_from_fen("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::promote(SQ_B7, SQ_B8, QUEEN)), 900);
+        let board = board_from_fen("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::promote(SQ_B7, SQ_B8, QUEEN)), -100);
+        assert_eq!(board.see(Move::promote(SQ_B7, SQ_A8, QUEEN)), 1400);
+    }
+
+    #[test]
+    pub fn see_king_cannot_recapture_defended_square() {
+        let board = board_from_fen("4k3/8/8/8/8/1n6/3p4/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::new(ROOK, SQ_D1, SQ_D2)), -75);
+        let board = board_from_fen("3rk3/8/8/8/8/1n6/3p4/3RK3 w - - 0 1").unwrap();
+        assert_eq!(board.see(Move::new(ROOK, SQ_D1, SQ_D2)), -400);
+    }
+}
@@ -0,0 +1,252 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::board::Board;
+use crate::core::fen::{board_from_fen, char_to_piece, en_passant_square, to_string};
+use crate::core::packed_board::PackedBoard;
+use crate::core::r#move::Move;
+use crate::core::square::Square;
+use crate::core::*;
+use serde::de::{DeserializeSeed, Error, Unexpected};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+// Human readable formats (JSON) get the notation a chess player would write: squares like `e4`,
+// pieces as their FEN letter, boards as FEN strings. Binary formats get compact integers and
+// boards packed in 32 bytes.
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u8(self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            match en_passant_square(&s) {
+                Ok(Some(square)) => Ok(square),
+                _ => Err(D::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &"a square like e4",
+                )),
+            }
+        } else {
+            let index = u8::deserialize(deserializer)?;
+            if index < 64 {
+                Ok(Square(index))
+            } else {
+                let unexpected = Unexpected::Unsigned(index as u64);
+                Err(D::Error::invalid_value(
+                    unexpected,
+                    &"a square index below 64",
+                ))
+            }
+        }
+    }
+}
+
+impl Serialize for Piece {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.collect_str(self)
+        } else {
+            serializer.serialize_u8(*self as u8)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Piece {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            let mut chars = s.chars();
+            match (chars.next().map(char_to_piece), chars.next()) {
+                (Some(Ok(piece)), None) => Ok(piece),
+                _ => Err(D::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &"one of p, n, b, r, q, k",
+                )),
+            }
+        } else {
+            let value = u8::deserialize(deserializer)?;
+            if value <= KING as u8 {
+                Ok(Piece::from(value))
+            } else {
+                let unexpected = Unexpected::Unsigned(value as u64);
+                Err(D::Error::invalid_value(
+                    unexpected,
+                    &"a piece index below 6",
+                ))
+            }
+        }
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(match self {
+                WHITE => "white",
+                BLACK => "black",
+            })
+        } else {
+            serializer.serialize_u8(*self as u8)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            match s.as_str() {
+                "white" => Ok(WHITE),
+                "black" => Ok(BLACK),
+                _ => Err(D::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &"white or black",
+                )),
+            }
+        } else {
+            match u8::deserialize(deserializer)? {
+                0 => Ok(WHITE),
+                1 => Ok(BLACK),
+                value => {
+                    let unexpected = Unexpected::Unsigned(value as u64);
+                    Err(D::Error::invalid_value(unexpected, &"0 or 1"))
+                }
+            }
+        }
+    }
+}
+
+impl Serialize for BitBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for BitBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(BitBoard(u64::deserialize(deserializer)?))
+    }
+}
+
+impl Serialize for Board {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            // the board doesn't track the full move counter
+            serializer.serialize_str(&to_string(self, 1))
+        } else {
+            PackedBoard::pack(self)
+                .ok_or_else(|| S::Error::custom("too many pieces to pack the board"))?
+                .0
+                .serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Board {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let fen = String::deserialize(deserializer)?;
+            board_from_fen(&fen)
+                .ok_or_else(|| D::Error::invalid_value(Unexpected::Str(&fen), &"a FEN string"))
+        } else {
+            PackedBoard(<[u8; 32]>::deserialize(deserializer)?)
+                .unpack()
+                .ok_or_else(|| D::Error::custom("invalid packed board"))
+        }
+    }
+}
+
+impl Serialize for Move {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Deserializes a move written in UCI notation. The notation doesn't say which piece moves, so
+/// the move has to be resolved against the position it is played in.
+pub struct MoveSeed<'a>(pub &'a Board);
+
+impl<'de> DeserializeSeed<'de> for MoveSeed<'_> {
+    type Value = Move;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Move, D::Error> {
+        let uci = String::deserialize(deserializer)?;
+        Move::from_uci(&uci, self.0).ok_or_else(|| {
+            D::Error::invalid_value(Unexpected::Str(&uci), &"a legal move in UCI notation")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::bitboard::BitBoard;
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::core::serialization::MoveSeed;
+    use crate::core::square::Square;
+    use crate::core::square_constants::*;
+    use crate::core::*;
+    use serde::de::DeserializeSeed;
+
+    const FEN: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    #[test]
+    pub fn json_round_trip() {
+        let board = board_from_fen(FEN).unwrap();
+        let json = serde_json::to_string(&board).unwrap();
+        assert_eq!(json, format!("\"{}\"", FEN));
+        assert_eq!(serde_json::from_str::<Board>(&json).unwrap(), board);
+
+        assert_eq!(serde_json::to_string(&SQ_E4).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::from_str::<Square>("\"e4\"").unwrap(), SQ_E4);
+        assert_eq!(serde_json::to_string(&KNIGHT).unwrap(), "\"n\"");
+        assert_eq!(serde_json::from_str::<Piece>("\"n\"").unwrap(), KNIGHT);
+        assert_eq!(serde_json::to_string(&BLACK).unwrap(), "\"black\"");
+        assert_eq!(serde_json::from_str::<Color>("\"black\"").unwrap(), BLACK);
+        let bb = BitBoard::from_square(SQ_A1) | SQ_H8;
+        let json = serde_json::to_string(&bb).unwrap();
+        assert_eq!(serde_json::from_str::<BitBoard>(&json).unwrap(), bb);
+
+        assert!(serde_json::from_str::<Square>("\"i9\"").is_err());
+        assert!(serde_json::from_str::<Piece>("\"x\"").is_err());
+        assert!(serde_json::from_str::<Board>("\"not a fen\"").is_err());
+    }
+
+    #[test]
+    pub fn binary_round_trip() {
+        let mut board = board_from_fen(FEN).unwrap();
+        board.make_move(Move::new(PAWN, SQ_A2, SQ_A4));
+        let bytes = bincode::serialize(&board).unwrap();
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(bincode::deserialize::<Board>(&bytes).unwrap(), board);
+
+        let bytes = bincode::serialize(&SQ_G7).unwrap();
+        assert_eq!(bytes, vec![SQ_G7.0]);
+        assert_eq!(bincode::deserialize::<Square>(&bytes).unwrap(), SQ_G7);
+        assert!(bincode::deserialize::<Square>(&[64]).is_err());
+        assert!(bincode::deserialize::<Piece>(&[6]).is_err());
+    }
+
+    #[test]
+    pub fn move_round_trip() {
+        let board = board_from_fen(FEN).unwrap();
+        let m = Move::CastleQueenSideWhite;
+        let json = serde_json::to_string(&m).unwrap();
+        assert_eq!(json, "\"e1c1\"");
+        let mut deserializer = serde_json::Deserializer::from_str(&json);
+        let parsed = MoveSeed(&board).deserialize(&mut deserializer).unwrap();
+        assert_eq!(parsed.to_string(), m.to_string());
+
+        let mut deserializer = serde_json::Deserializer::from_str("\"e1e3\"");
+        assert!(MoveSeed(&board).deserialize(&mut deserializer).is_err());
+    }
+}
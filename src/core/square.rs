@@ -1,7 +1,7 @@
 use std::fmt::{Display, Formatter, Write};
-use std::ops::Index;
+use std::ops::{Index, IndexMut};
 
-#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
 pub struct Square(pub(crate) u8);
 
 impl Square {
@@ -71,6 +71,13 @@ impl<T> Index<Square> for [T; 64] {
     }
 }
 
+impl<T> IndexMut<Square> for [T; 64] {
+    #[inline]
+    fn index_mut(&mut self, index: Square) -> &mut Self::Output {
+        &mut self[index.0 as usize]
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::core::square::Square;
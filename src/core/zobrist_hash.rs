@@ -2,35 +2,11 @@ use lazy_static::lazy_static;
 use rand::{RngCore, SeedableRng};
 
 use crate::core::bitboard::BitBoard;
-use crate::core::bitboard_constants::*;
 use crate::core::board::Board;
 use crate::core::r#move::Move;
 use crate::core::square::Square;
-use crate::core::square_constants::*;
-use crate::core::zobrist_hash::CastlingSide::{KingSide, QueenSide};
 use crate::core::*;
 
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-#[repr(u8)]
-enum CastlingSide {
-    KingSide,
-    QueenSide,
-}
-
-impl<T> Index<CastlingSide> for [T; 2] {
-    type Output = T;
-
-    fn index(&self, index: CastlingSide) -> &Self::Output {
-        &self[index as usize]
-    }
-}
-
-impl<T> IndexMut<CastlingSide> for [T; 2] {
-    fn index_mut(&mut self, index: CastlingSide) -> &mut Self::Output {
-        &mut self[index as usize]
-    }
-}
-
 struct ZobristTable {
     pieces: [[[u64; 2]; 6]; 64],
     side: u64,
@@ -103,6 +79,19 @@ pub fn hash(board: &Board) -> u64 {
     h
 }
 
+/// Hash of the pawns only, to cache evaluation terms that depend on the pawn structure.
+pub fn pawn_hash(board: &Board) -> u64 {
+    let mut h: u64 = 0;
+    for color in 0..2 {
+        let mut remaining = board.piece_of_color[color] & board.piece_of_type[PAWN as usize];
+        while !remaining.empty() {
+            let sq = remaining.pop_lsb();
+            h ^= ZOBRIST_TABLE.pieces[sq][PAWN][color];
+        }
+    }
+    h
+}
+
 pub fn hash_update(board: &Board, prev: u64, m: Move) -> u64 {
     let mut hash = prev;
     hash ^= ZOBRIST_TABLE.side;
@@ -115,10 +104,12 @@ pub fn hash_update(board: &Board, prev: u64, m: Move) -> u64 {
             origin,
             dest,
         } => hash_update_normal(board, hash, piece, origin, dest),
-        Move::CastleKingSideWhite => hash_update_castle_king_side_white(hash),
-        Move::CastleQueenSideWhite => hash_update_castle_queen_side_white(hash),
-        Move::CastleKingSideBlack => hash_update_castle_king_side_black(hash),
-        Move::CastleQueenSideBlack => hash_update_castle_queen_side_black(hash),
+        Move::CastleKingSideWhite | Move::CastleKingSideBlack => {
+            hash_update_castle(board, hash, KingSide)
+        }
+        Move::CastleQueenSideWhite | Move::CastleQueenSideBlack => {
+            hash_update_castle(board, hash, QueenSide)
+        }
         Move::Promotion {
             origin,
             dest,
@@ -176,10 +167,7 @@ fn hash_update_promotion(
                 hash ^ ZOBRIST_TABLE.pieces[origin][PAWN][board.side_to_play]
                     ^ ZOBRIST_TABLE.pieces[dest][piece][board.side_to_play]
                     ^ ZOBRIST_TABLE.pieces[dest][capture][opposite],
-                [
-                    [dest_bb * BB_H1, dest_bb * BB_H8],
-                    [dest_bb * BB_A1, dest_bb * BB_A8],
-                ],
+                castling_rights_loss(board, dest_bb),
             );
         }
     }
@@ -191,47 +179,32 @@ fn hash_update_promotion(
 }
 
 #[inline]
-fn hash_update_castle_king_side_white(hash: u64) -> (u64, [[bool; 2]; 2]) {
+fn hash_update_castle(board: &Board, hash: u64, side: CastlingSide) -> (u64, [[bool; 2]; 2]) {
+    let us = board.side_to_play;
+    let (king_origin, king_dest, rook_origin, rook_dest) = board.castling_squares(us, side);
+    let mut loss = [[false; 2]; 2];
+    loss[KingSide][us] = true;
+    loss[QueenSide][us] = true;
     (
-        hash ^ ZOBRIST_TABLE.pieces[SQ_E1][KING][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_G1][KING][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_F1][ROOK][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_H1][ROOK][WHITE],
-        [[true, false], [true, false]],
+        hash ^ ZOBRIST_TABLE.pieces[king_origin][KING][us]
+            ^ ZOBRIST_TABLE.pieces[king_dest][KING][us]
+            ^ ZOBRIST_TABLE.pieces[rook_origin][ROOK][us]
+            ^ ZOBRIST_TABLE.pieces[rook_dest][ROOK][us],
+        loss,
     )
 }
 
+/// The castling rights lost by a move touching `squares`: moving a king or moving or capturing
+/// a castling rook.
 #[inline]
-fn hash_update_castle_king_side_black(hash: u64) -> (u64, [[bool; 2]; 2]) {
-    (
-        hash ^ ZOBRIST_TABLE.pieces[SQ_E8][KING][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_G8][KING][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_F8][ROOK][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_H8][ROOK][BLACK],
-        [[false, true], [false, true]],
-    )
-}
-
-#[inline]
-fn hash_update_castle_queen_side_white(hash: u64) -> (u64, [[bool; 2]; 2]) {
-    (
-        hash ^ ZOBRIST_TABLE.pieces[SQ_E1][KING][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_C1][KING][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_D1][ROOK][WHITE]
-            ^ ZOBRIST_TABLE.pieces[SQ_A1][ROOK][WHITE],
-        [[true, false], [true, false]],
-    )
-}
-
-#[inline]
-fn hash_update_castle_queen_side_black(hash: u64) -> (u64, [[bool; 2]; 2]) {
-    (
-        hash ^ ZOBRIST_TABLE.pieces[SQ_E8][KING][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_C8][KING][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_D8][ROOK][BLACK]
-            ^ ZOBRIST_TABLE.pieces[SQ_A8][ROOK][BLACK],
-        [[false, true], [false, true]],
-    )
+fn castling_rights_loss(board: &Board, squares: BitBoard) -> [[bool; 2]; 2] {
+    let mut loss = [[false; 2]; 2];
+    for color in [WHITE, BLACK] {
+        let king = squares * board.king_pos[color];
+        loss[KingSide][color] = king || squares * board.castling_rook_square(color, KingSide);
+        loss[QueenSide][color] = king || squares * board.castling_rook_square(color, QueenSide);
+    }
+    loss
 }
 
 fn hash_update_normal(
@@ -254,34 +227,37 @@ fn hash_update_normal(
             hash ^= ZOBRIST_TABLE.en_passant[origin.file() as usize];
         }
     }
-    let both = origin_bb | dest_bb;
-    (
-        hash,
-        [
-            [
-                (both * BB_H1) || origin == SQ_E1,
-                (both * BB_H8) || origin == SQ_E8,
-            ],
-            [
-                (both * BB_A1) || origin == SQ_E1,
-                (both * BB_A8) || origin == SQ_E8,
-            ],
-        ],
-    )
+    (hash, castling_rights_loss(board, origin_bb | dest_bb))
 }
 
 #[cfg(test)]
 mod tests {
     use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
     use crate::core::move_generator::MoveGenerator;
-    use crate::core::zobrist_hash::{hash, hash_update};
+    use crate::core::square_constants::*;
+    use crate::core::zobrist_hash::{hash, hash_update, pawn_hash};
     use rand::{RngCore, SeedableRng};
 
+    #[test]
+    pub fn pawn_hash_ignores_pieces() {
+        let board = Board::from_initial_position();
+        let other = board_from_fen("r3k2r/pppppppp/8/8/8/8/PPPPPPPP/4K3 b kq - 4 9").unwrap();
+        assert_eq!(pawn_hash(&board), pawn_hash(&other));
+        let moved = board_from_fen("4k3/pppppppp/8/8/4P3/8/PPPP1PPP/4K3 w - - 0 1").unwrap();
+        assert_ne!(pawn_hash(&board), pawn_hash(&moved));
+        assert_eq!(pawn_hash(&Board::empty(SQ_E1, SQ_E8)), 0);
+    }
+
     #[test]
     pub fn test() {
         let mut rng = rand::rngs::StdRng::seed_from_u64(42);
-        for _i in 0..20000 {
-            let mut board = Board::from_initial_position();
+        for i in 0..20000 {
+            let mut board = if i % 2 == 0 {
+                Board::from_initial_position()
+            } else {
+                Board::from_chess960_position((rng.next_u64() % 960) as u16)
+            };
             let mut h = hash(&board);
             for _j in 0..300 {
                 let mut mg = MoveGenerator::new(&board);
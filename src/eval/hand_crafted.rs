@@ -0,0 +1,183 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::bitboard_attacks::knight_attacks;
+use crate::core::bitboard_constants::*;
+use crate::core::board::Board;
+use crate::core::magic_bitboard::{bishop_attacks, rook_attacks};
+use crate::core::Color::*;
+use crate::core::{Color, Piece};
+use crate::eval::pawns::{pawn_attacks_bb, PawnHashTable, PawnStructure};
+use crate::eval::psqt::psqt;
+use crate::eval::*;
+
+const BISHOP_PAIR: Score = Score::new(30, 50);
+
+/// Bonus of a passed pawn by rank, from the point of view of its color.
+const PASSED_PAWN: [Score; 8] = [
+    Score::new(0, 0),
+    Score::new(2, 8),
+    Score::new(5, 12),
+    Score::new(10, 20),
+    Score::new(25, 40),
+    Score::new(50, 80),
+    Score::new(80, 130),
+    Score::new(0, 0),
+];
+const CANDIDATE_PAWN: Score = Score::new(5, 15);
+const CONNECTED_PAWN: Score = Score::new(5, 5);
+const ISOLATED_PAWN: Score = Score::new(-10, -15);
+const DOUBLED_PAWN: Score = Score::new(-10, -20);
+const BACKWARD_PAWN: Score = Score::new(-8, -10);
+
+/// Bonus per square a piece can move to beyond a typical number of squares, and that number.
+/// Squares occupied by friendly pieces or attacked by enemy pawns don't count.
+const MOBILITY: [(Piece, Score, i32); 4] = [
+    (KNIGHT, Score::new(4, 4), 4),
+    (BISHOP, Score::new(5, 5), 7),
+    (ROOK, Score::new(2, 4), 7),
+    (QUEEN, Score::new(1, 2), 14),
+];
+
+/// Default evaluation: material and piece-square tables interpolated between the middlegame
+/// and the endgame, bishop pair, mobility and pawn structure. It is symmetric, the position with
+/// the colors flipped gets the opposite score.
+#[derive(Clone, Default)]
+pub struct HandCraftedEvaluator {
+    pawn_table: PawnHashTable,
+}
+
+impl HandCraftedEvaluator {
+    /// Scores `board` in centipawns from White's point of view.
+    pub fn evaluate_for_white(&mut self, board: &Board) -> i32 {
+        let pawns = self.pawn_table.get(board);
+        let score = side_score(board, pawns, WHITE) - side_score(board, pawns, BLACK);
+        score.taper(game_phase(board))
+    }
+}
+
+impl Evaluator for HandCraftedEvaluator {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let score = self.evaluate_for_white(board);
+        match board.side_to_play {
+            WHITE => score,
+            BLACK => -score,
+        }
+    }
+}
+
+fn side_score(board: &Board, pawns: &PawnStructure, color: Color) -> Score {
+    let ours = board.piece_of_color(color);
+    let mut score = psqt(KING, color, board.king_pos(color));
+    for piece in [PAWN, KNIGHT, BISHOP, ROOK, QUEEN] {
+        let mut remaining = board.piece_of_type(piece) & ours;
+        while !remaining.empty() {
+            score += psqt(piece, color, remaining.pop_lsb());
+        }
+    }
+    let bishops = board.piece_of_type(BISHOP) & ours;
+    if bishops * DARK_SQUARES && bishops * LIGHT_SQUARES {
+        score += BISHOP_PAIR;
+    }
+    score + mobility(board, color) + pawn_structure(pawns, color)
+}
+
+fn pawn_structure(pawns: &PawnStructure, color: Color) -> Score {
+    let count = |bb: BitBoard| bb.num_squares() as i32;
+    let mut score = CANDIDATE_PAWN * count(pawns.candidates[color])
+        + CONNECTED_PAWN * count(pawns.connected[color])
+        + ISOLATED_PAWN * count(pawns.isolated[color])
+        + DOUBLED_PAWN * count(pawns.doubled[color])
+        + BACKWARD_PAWN * count(pawns.backward[color]);
+    let mut passed = pawns.passed[color];
+    while !passed.empty() {
+        let rank = passed.pop_lsb().rank();
+        score += PASSED_PAWN[match color {
+            WHITE => rank,
+            BLACK => 7 - rank,
+        } as usize];
+    }
+    score
+}
+
+fn mobility(board: &Board, color: Color) -> Score {
+    let ours = board.piece_of_color(color);
+    let their_pawns = board.piece_of_opposite_color(color) & board.piece_of_type(PAWN);
+    let area = !(ours | pawn_attacks_bb(color.opposite(), their_pawns));
+    let occupancy = board.occupancy();
+
+    let mut score = Score::ZERO;
+    for (piece, bonus, typical) in MOBILITY {
+        let mut remaining = board.piece_of_type(piece) & ours;
+        while !remaining.empty() {
+            let square = remaining.pop_lsb();
+            let attacks = match piece {
+                KNIGHT => knight_attacks(square),
+                BISHOP => bishop_attacks(square, occupancy),
+                ROOK => rook_attacks(square, occupancy),
+                _ => bishop_attacks(square, occupancy) | rook_attacks(square, occupancy),
+            };
+            score += bonus * ((attacks & area).num_squares() as i32 - typical);
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::eval::hand_crafted::HandCraftedEvaluator;
+    use crate::eval::*;
+    use rand::{RngCore, SeedableRng};
+
+    #[test]
+    pub fn initial_position_is_balanced() {
+        let mut evaluator = HandCraftedEvaluator::default();
+        let board = Board::from_initial_position();
+        assert_eq!(evaluator.evaluate(&board), 0);
+        assert_eq!(game_phase(&board), MAX_PHASE);
+    }
+
+    #[test]
+    pub fn color_flip_symmetry() {
+        let mut evaluator = HandCraftedEvaluator::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for _i in 0..100 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..100 {
+                let flipped = board.color_flipped();
+                assert_eq!(
+                    evaluator.evaluate_for_white(&flipped),
+                    -evaluator.evaluate_for_white(&board)
+                );
+                assert_eq!(evaluator.evaluate(&flipped), evaluator.evaluate(&board));
+
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                board.make_move(mg.moves[rng.next_u64() as usize % mg.moves.len()]);
+            }
+        }
+    }
+
+    #[test]
+    pub fn evaluation_terms() {
+        let mut evaluator = HandCraftedEvaluator::default();
+        // a centralized knight beats one on the rim
+        let center = board_from_fen("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        let rim = board_from_fen("4k3/8/8/8/N7/8/8/4K3 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&center) > evaluator.evaluate(&rim));
+
+        // two bishops on the same color don't make a pair
+        let pair = board_from_fen("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1").unwrap();
+        let same_color = board_from_fen("4k3/8/8/8/8/8/8/1B2KB2 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&pair) > evaluator.evaluate(&same_color) + 30);
+
+        // a pawn is worth more in the endgame, from the point of view of the side to play
+        let board = board_from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&board) < -90);
+        assert_eq!(game_phase(&board), 0);
+    }
+}
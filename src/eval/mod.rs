@@ -0,0 +1,113 @@
+use crate::core::board::Board;
+use crate::core::see::SEE_VALUE;
+use crate::core::Piece::*;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+pub mod hand_crafted;
+pub mod pawns;
+pub mod psqt;
+
+/// Static evaluation used at the leaves of the search. Implement it to plug another evaluation
+/// into the search.
+pub trait Evaluator {
+    /// Scores `board` in centipawns from the point of view of the side to play.
+    fn evaluate(&mut self, board: &Board) -> i32;
+}
+
+/// Counts material only.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MaterialEvaluator;
+
+impl Evaluator for MaterialEvaluator {
+    fn evaluate(&mut self, board: &Board) -> i32 {
+        let us = board.piece_of_color(board.side_to_play);
+        let them = board.piece_of_opposite_color(board.side_to_play);
+        [PAWN, KNIGHT, BISHOP, ROOK, QUEEN]
+            .iter()
+            .map(|&piece| {
+                let pieces = board.piece_of_type(piece);
+                let balance =
+                    (pieces & us).num_squares() as i32 - (pieces & them).num_squares() as i32;
+                balance * SEE_VALUE[piece]
+            })
+            .sum()
+    }
+}
+
+/// Game phase of the opening, when all the pieces are on the board.
+pub const MAX_PHASE: i32 = 24;
+
+/// Game phase from `MAX_PHASE` in the opening down to 0 with only kings and pawns left, counting
+/// 1 per minor piece, 2 per rook and 4 per queen.
+pub fn game_phase(board: &Board) -> i32 {
+    let phase: u32 = [(KNIGHT, 1), (BISHOP, 1), (ROOK, 2), (QUEEN, 4)]
+        .iter()
+        .map(|&(piece, weight)| board.piece_of_type(piece).num_squares() * weight)
+        .sum();
+    (phase as i32).min(MAX_PHASE)
+}
+
+/// A pair of middlegame and endgame scores, interpolated by game phase.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Score {
+    pub const ZERO: Score = Score { mg: 0, eg: 0 };
+
+    pub const fn new(mg: i32, eg: i32) -> Score {
+        Score { mg, eg }
+    }
+
+    /// Interpolates between the middlegame score at `MAX_PHASE` and the endgame score at 0.
+    /// Rounds toward zero, so that the score of the other color is the exact negation.
+    pub const fn taper(self, phase: i32) -> i32 {
+        (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+}
+
+impl Add for Score {
+    type Output = Score;
+
+    fn add(self, rhs: Score) -> Score {
+        Score::new(self.mg + rhs.mg, self.eg + rhs.eg)
+    }
+}
+
+impl AddAssign for Score {
+    fn add_assign(&mut self, rhs: Score) {
+        *self = *self + rhs;
+    }
+}
+
+impl Sub for Score {
+    type Output = Score;
+
+    fn sub(self, rhs: Score) -> Score {
+        Score::new(self.mg - rhs.mg, self.eg - rhs.eg)
+    }
+}
+
+impl SubAssign for Score {
+    fn sub_assign(&mut self, rhs: Score) {
+        *self = *self - rhs;
+    }
+}
+
+impl Neg for Score {
+    type Output = Score;
+
+    fn neg(self) -> Score {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<i32> for Score {
+    type Output = Score;
+
+    fn mul(self, rhs: i32) -> Score {
+        Score::new(self.mg * rhs, self.eg * rhs)
+    }
+}
@@ -0,0 +1,323 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::bitboard_constants::*;
+use crate::core::board::Board;
+use crate::core::zobrist_hash::pawn_hash;
+use crate::core::Color;
+use crate::core::Color::*;
+use crate::core::Piece::*;
+
+// Pawn structure terms of `color`, computed from its pawns `ours` and the opponent's pawns
+// `theirs`. Every function returns a bitboard of pawns, except the file functions which return
+// whole files.
+
+/// Pawns with no enemy pawn in front of them on their file or the adjacent files. Only the front
+/// pawn of doubled passed pawns counts.
+pub fn passed_pawns(color: Color, ours: BitBoard, theirs: BitBoard) -> BitBoard {
+    let their_front_spans = front_span(color.opposite(), theirs);
+    ours & !(their_front_spans | adjacent_files(their_front_spans)) & !rear_span(color, ours)
+}
+
+/// Pawns with no friendly pawn on the adjacent files.
+pub fn isolated_pawns(ours: BitBoard) -> BitBoard {
+    ours & !file_fill(adjacent_files(ours))
+}
+
+/// Pawns with a friendly pawn behind them on the same file, that is every pawn of a file but the
+/// rearmost one.
+pub fn doubled_pawns(color: Color, ours: BitBoard) -> BitBoard {
+    ours & front_span(color, ours)
+}
+
+/// Pawns whose stop square is attacked by an enemy pawn and can't be defended by friendly pawns,
+/// as they are all further advanced on the adjacent files.
+pub fn backward_pawns(color: Color, ours: BitBoard, theirs: BitBoard) -> BitBoard {
+    let our_attacks = pawn_attacks_bb(color, ours);
+    let supported = our_attacks | front_span(color, our_attacks);
+    let stops = ours.shift(color.fwd_dir());
+    let weak_stops = stops & pawn_attacks_bb(color.opposite(), theirs) & !supported;
+    ours & weak_stops.shift(-color.fwd_dir())
+}
+
+/// Pawns defended by a friendly pawn or with a friendly pawn beside them.
+pub fn connected_pawns(color: Color, ours: BitBoard) -> BitBoard {
+    ours & (adjacent_files(ours) | pawn_attacks_bb(color, ours))
+}
+
+/// Pawns that aren't passed yet but could become passed: no enemy pawn in front of them on their
+/// file, and at least as many friendly pawns beside or behind them on the adjacent files as
+/// enemy pawns in front of them there.
+pub fn candidate_passed_pawns(color: Color, ours: BitBoard, theirs: BitBoard) -> BitBoard {
+    let mut candidates = BitBoard::EMPTY;
+    let mut remaining =
+        ours & !passed_pawns(color, ours, theirs) & !rear_span(color, ours | theirs);
+    while !remaining.empty() {
+        let square = remaining.pop_lsb();
+        let pawn = BitBoard::from_square(square);
+        let front = front_span(color, pawn);
+        let sentries = theirs & adjacent_files(front);
+        let helpers = ours & adjacent_files(file_fill(pawn)) & !adjacent_files(front);
+        if helpers.num_squares() >= sentries.num_squares() {
+            candidates |= pawn;
+        }
+    }
+    candidates
+}
+
+/// Groups of pawns on adjacent files, from the a-file to the h-file.
+pub fn pawn_islands(ours: BitBoard) -> Vec<BitBoard> {
+    let mut islands = Vec::new();
+    let mut island = BitBoard::EMPTY;
+    for file in FILE {
+        let pawns = ours & file;
+        if pawns.empty() {
+            if !island.empty() {
+                islands.push(island);
+            }
+            island = BitBoard::EMPTY;
+        } else {
+            island |= pawns;
+        }
+    }
+    if !island.empty() {
+        islands.push(island);
+    }
+    islands
+}
+
+/// Files without pawns.
+pub fn open_files(pawns: BitBoard) -> BitBoard {
+    !file_fill(pawns)
+}
+
+/// Files without friendly pawns but with enemy pawns.
+pub fn half_open_files(ours: BitBoard, theirs: BitBoard) -> BitBoard {
+    !file_fill(ours) & file_fill(theirs)
+}
+
+/// Squares in front of `pawns` from the point of view of `color`, excluding their own squares.
+pub const fn front_span(color: Color, pawns: BitBoard) -> BitBoard {
+    match color {
+        WHITE => north_fill(BitBoard(pawns.0 << 8)),
+        BLACK => south_fill(BitBoard(pawns.0 >> 8)),
+    }
+}
+
+/// Squares behind `pawns` from the point of view of `color`, excluding their own squares.
+pub const fn rear_span(color: Color, pawns: BitBoard) -> BitBoard {
+    front_span(color.opposite(), pawns)
+}
+
+pub const fn file_fill(bb: BitBoard) -> BitBoard {
+    BitBoard(north_fill(bb).0 | south_fill(bb).0)
+}
+
+/// The squares beside `bb` on the adjacent files.
+pub const fn adjacent_files(bb: BitBoard) -> BitBoard {
+    BitBoard((bb.0 & FILE_A_I.0) >> 1 | (bb.0 & FILE_H_I.0) << 1)
+}
+
+/// Squares attacked by `pawns` of `color`.
+pub const fn pawn_attacks_bb(color: Color, pawns: BitBoard) -> BitBoard {
+    adjacent_files(pawns).shift(color.fwd_dir())
+}
+
+const fn north_fill(bb: BitBoard) -> BitBoard {
+    let mut b = bb.0;
+    b |= b << 8;
+    b |= b << 16;
+    b |= b << 32;
+    BitBoard(b)
+}
+
+const fn south_fill(bb: BitBoard) -> BitBoard {
+    let mut b = bb.0;
+    b |= b >> 8;
+    b |= b >> 16;
+    b |= b >> 32;
+    BitBoard(b)
+}
+
+/// Every pawn structure term of both colors, indexed by color.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PawnStructure {
+    pub passed: [BitBoard; 2],
+    pub isolated: [BitBoard; 2],
+    pub doubled: [BitBoard; 2],
+    pub backward: [BitBoard; 2],
+    pub connected: [BitBoard; 2],
+    pub candidates: [BitBoard; 2],
+    pub islands: [Vec<BitBoard>; 2],
+    pub open_files: BitBoard,
+    pub half_open_files: [BitBoard; 2],
+}
+
+impl PawnStructure {
+    pub fn new(board: &Board) -> PawnStructure {
+        let pawns = board.piece_of_type(PAWN);
+        let by_color = [
+            pawns & board.piece_of_color(WHITE),
+            pawns & board.piece_of_color(BLACK),
+        ];
+        let each = |term: &dyn Fn(Color, BitBoard, BitBoard) -> BitBoard| {
+            [
+                term(WHITE, by_color[0], by_color[1]),
+                term(BLACK, by_color[1], by_color[0]),
+            ]
+        };
+        PawnStructure {
+            passed: each(&passed_pawns),
+            isolated: each(&|_, ours, _| isolated_pawns(ours)),
+            doubled: each(&|color, ours, _| doubled_pawns(color, ours)),
+            backward: each(&backward_pawns),
+            connected: each(&|color, ours, _| connected_pawns(color, ours)),
+            candidates: each(&candidate_passed_pawns),
+            islands: by_color.map(pawn_islands),
+            open_files: open_files(pawns),
+            half_open_files: each(&|_, ours, theirs| half_open_files(ours, theirs)),
+        }
+    }
+}
+
+/// Cache of pawn structures keyed by the pawn hash. Pawn structures change much less often than
+/// positions, so most lookups of a search hit.
+#[derive(Clone)]
+pub struct PawnHashTable {
+    entries: Vec<Option<(u64, PawnStructure)>>,
+}
+
+impl PawnHashTable {
+    pub const DEFAULT_ENTRIES: usize = 1 << 12;
+
+    /// Creates a table of `entries` entries, rounded down to a power of two.
+    pub fn new(entries: usize) -> PawnHashTable {
+        let entries = 1 << (usize::BITS - 1 - entries.max(1).leading_zeros());
+        PawnHashTable {
+            entries: vec![None; entries],
+        }
+    }
+
+    /// The pawn structure of `board`, computed unless it is cached.
+    pub fn get(&mut self, board: &Board) -> &PawnStructure {
+        let key = pawn_hash(board);
+        let index = key as usize & (self.entries.len() - 1);
+        let entry = &mut self.entries[index];
+        if !matches!(entry, Some((entry_key, _)) if *entry_key == key) {
+            *entry = Some((key, PawnStructure::new(board)));
+        }
+        &entry.as_ref().unwrap().1
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+
+impl Default for PawnHashTable {
+    fn default() -> Self {
+        PawnHashTable::new(PawnHashTable::DEFAULT_ENTRIES)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::bitboard::BitBoard;
+    use crate::core::bitboard_constants::*;
+    use crate::core::board::Board;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::core::square::Square;
+    use crate::core::square_constants::*;
+    use crate::eval::pawns::*;
+    use rand::{RngCore, SeedableRng};
+
+    fn squares(squares: &[Square]) -> BitBoard {
+        squares
+            .iter()
+            .fold(BitBoard::EMPTY, |bb, &square| bb | square)
+    }
+
+    #[test]
+    pub fn passed_and_candidates() {
+        let white = squares(&[SQ_A5, SQ_D4, SQ_D2, SQ_H5]);
+        let black = squares(&[SQ_B7, SQ_E6, SQ_G3]);
+        assert_eq!(passed_pawns(WHITE, white, black), squares(&[SQ_H5]));
+        assert_eq!(passed_pawns(BLACK, black, white), squares(&[SQ_G3]));
+
+        // one helper against one sentry
+        let white = squares(&[SQ_C4, SQ_D4]);
+        let black = squares(&[SQ_D5]);
+        assert_eq!(
+            candidate_passed_pawns(WHITE, white, black),
+            squares(&[SQ_C4])
+        );
+        let black = squares(&[SQ_D5, SQ_B6]);
+        assert_eq!(candidate_passed_pawns(WHITE, white, black), BitBoard::EMPTY);
+    }
+
+    #[test]
+    pub fn weak_pawns() {
+        let white = squares(&[SQ_A2, SQ_C2, SQ_C3, SQ_E4, SQ_F3]);
+        assert_eq!(isolated_pawns(white), squares(&[SQ_A2, SQ_C2, SQ_C3]));
+        assert_eq!(doubled_pawns(WHITE, white), squares(&[SQ_C3]));
+        assert_eq!(doubled_pawns(BLACK, white), squares(&[SQ_C2]));
+
+        // the d-pawn can't advance safely and the c- and e-pawns are too far to defend it
+        let white = squares(&[SQ_C4, SQ_D3, SQ_E4]);
+        let black = squares(&[SQ_E5]);
+        assert_eq!(backward_pawns(WHITE, white, black), squares(&[SQ_D3]));
+        assert_eq!(
+            backward_pawns(WHITE, white, BitBoard::EMPTY),
+            BitBoard::EMPTY
+        );
+
+        let white = squares(&[SQ_A2, SQ_B3, SQ_D4, SQ_E4, SQ_G2]);
+        assert_eq!(
+            connected_pawns(WHITE, white),
+            squares(&[SQ_B3, SQ_D4, SQ_E4])
+        );
+    }
+
+    #[test]
+    pub fn islands_and_files() {
+        let white = squares(&[SQ_A2, SQ_B2, SQ_D2, SQ_F2, SQ_G2, SQ_H2]);
+        assert_eq!(
+            pawn_islands(white),
+            vec![
+                squares(&[SQ_A2, SQ_B2]),
+                squares(&[SQ_D2]),
+                squares(&[SQ_F2, SQ_G2, SQ_H2])
+            ]
+        );
+        assert!(pawn_islands(BitBoard::EMPTY).is_empty());
+
+        let white = squares(&[SQ_A2, SQ_E4]);
+        let black = squares(&[SQ_A7, SQ_D5]);
+        assert_eq!(
+            open_files(white | black),
+            FILE_B | FILE_C | FILE_F | FILE_G | FILE_H
+        );
+        assert_eq!(half_open_files(white, black), FILE_D);
+        assert_eq!(half_open_files(black, white), FILE_E);
+    }
+
+    #[test]
+    pub fn pawn_hash_table() {
+        let mut table = PawnHashTable::new(16);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        for _i in 0..20 {
+            let mut board = Board::from_initial_position();
+            for _j in 0..100 {
+                assert_eq!(table.get(&board), &PawnStructure::new(&board));
+                let mut mg = MoveGenerator::new(&board);
+                mg.generate();
+                if mg.moves.is_empty() {
+                    break;
+                }
+                board.make_move(mg.moves[rng.next_u64() as usize % mg.moves.len()]);
+            }
+        }
+        let structure = table.get(&Board::from_initial_position()).clone();
+        assert_eq!(structure.islands[WHITE], vec![RANK_2]);
+        assert_eq!(structure.passed, [BitBoard::EMPTY; 2]);
+        assert_eq!(structure.open_files, BitBoard::EMPTY);
+    }
+}
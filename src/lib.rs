@@ -1 +1,7 @@
 pub mod core;
+pub mod eval;
+pub mod problem;
+pub mod search;
+pub mod tournament;
+pub mod uci;
+pub mod xboard;
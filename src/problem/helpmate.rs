@@ -0,0 +1,222 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::{hash, hash_update};
+use crate::problem::{is_mated, legal_moves, Defence, SolutionNode};
+use std::collections::HashMap;
+
+/// The solutions of a helpmate problem. Each solution tree starts with a move of the side
+/// getting mated, and only keeps the moves of both sides going on with the solution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HelpmateSolution {
+    /// The number of moves of each side.
+    pub moves: u32,
+    /// Every first move starting a helpmate in at most `moves` moves, with its solution tree.
+    pub solutions: Vec<SolutionNode>,
+}
+
+impl HelpmateSolution {
+    /// Whether the problem has a single solution.
+    pub fn is_sound(&self) -> bool {
+        self.solutions.len() == 1
+    }
+
+    /// Whether a solution can go on in more than one way after its first move, a dual. Both
+    /// sides choose their moves, so a choice of either side is a dual.
+    pub fn has_duals(&self) -> bool {
+        self.solutions.iter().any(has_choices)
+    }
+
+    /// The solutions as lines of moves, one per way of playing each solution.
+    pub fn lines(&self) -> Vec<Vec<Move>> {
+        let mut lines = Vec::new();
+        for solution in self.solutions.iter() {
+            add_lines(&mut lines, &mut Vec::new(), solution);
+        }
+        lines
+    }
+
+    /// Writes every solution tree from `board`.
+    pub fn to_text(&self, board: &Board) -> String {
+        self.solutions
+            .iter()
+            .map(|solution| solution.to_text(board, 1))
+            .collect()
+    }
+}
+
+/// Whether either side has more than one move going on with the solution after `node`.
+fn has_choices(node: &SolutionNode) -> bool {
+    node.defences.len() > 1
+        || node.defences.iter().any(|defence| {
+            defence.continuations.len() > 1 || defence.continuations.iter().any(has_choices)
+        })
+}
+
+fn add_lines(lines: &mut Vec<Vec<Move>>, line: &mut Vec<Move>, node: &SolutionNode) {
+    line.push(node.attack);
+    for defence in node.defences.iter() {
+        line.push(defence.reply);
+        if defence.continuations.is_empty() {
+            lines.push(line.clone());
+        }
+        for continuation in defence.continuations.iter() {
+            add_lines(lines, line, continuation);
+        }
+        line.pop();
+    }
+    line.pop();
+}
+
+/// Finds the helpmates in N moves ("h#N"): the side to play moves first and both sides
+/// cooperate so that the other side mates it with its Nth move. The mating move is looked for
+/// among checks only. Results are kept by position and number of moves.
+#[derive(Clone, Debug, Default)]
+pub struct HelpmateSolver {
+    table: HashMap<(u64, u32), bool>,
+    nodes: u64,
+}
+
+impl HelpmateSolver {
+    pub fn new() -> HelpmateSolver {
+        HelpmateSolver::default()
+    }
+
+    /// The positions searched since the solver was created.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Whether the side to play can be helpmated in at most `moves` moves.
+    pub fn helpmates_in(&mut self, board: &Board, moves: u32) -> bool {
+        moves > 0 && self.search(board, hash(board), moves)
+    }
+
+    /// Finds every helpmate in at most `moves` moves, as solution trees.
+    pub fn solve(&mut self, board: &Board, moves: u32) -> HelpmateSolution {
+        let solutions = if moves == 0 {
+            Vec::new()
+        } else {
+            self.solutions(board, hash(board), moves)
+        };
+        HelpmateSolution { moves, solutions }
+    }
+
+    fn search(&mut self, board: &Board, hash: u64, moves: u32) -> bool {
+        if let Some(&mates) = self.table.get(&(hash, moves)) {
+            return mates;
+        }
+        self.nodes += 1;
+        let mut mates = false;
+        for m in legal_moves(board) {
+            let mut child = *board;
+            child.make_move(m);
+            let child_hash = hash_update(board, hash, m);
+            if self
+                .mating_moves(&child, moves)
+                .into_iter()
+                .any(|reply| self.mates_after(&child, child_hash, reply, moves))
+            {
+                mates = true;
+                break;
+            }
+        }
+        self.table.insert((hash, moves), mates);
+        mates
+    }
+
+    /// Whether `reply` mates, or leads to a helpmate in `moves - 1` moves.
+    fn mates_after(&mut self, board: &Board, hash: u64, reply: Move, moves: u32) -> bool {
+        let mut next = *board;
+        next.make_move(reply);
+        is_mated(&next)
+            || (moves > 1 && self.search(&next, hash_update(board, hash, reply), moves - 1))
+    }
+
+    /// The trees of the helpmates in at most `moves` moves.
+    fn solutions(&mut self, board: &Board, hash: u64, moves: u32) -> Vec<SolutionNode> {
+        let mut solutions = Vec::new();
+        for m in legal_moves(board) {
+            let mut child = *board;
+            child.make_move(m);
+            let child_hash = hash_update(board, hash, m);
+            let mut defences = Vec::new();
+            for reply in self.mating_moves(&child, moves) {
+                if !self.mates_after(&child, child_hash, reply, moves) {
+                    continue;
+                }
+                let mut next = child;
+                next.make_move(reply);
+                let continuations = if is_mated(&next) {
+                    Vec::new()
+                } else {
+                    let next_hash = hash_update(&child, child_hash, reply);
+                    self.solutions(&next, next_hash, moves - 1)
+                };
+                defences.push(Defence {
+                    reply,
+                    continuations,
+                });
+            }
+            if !defences.is_empty() {
+                solutions.push(SolutionNode {
+                    attack: m,
+                    defences,
+                });
+            }
+        }
+        solutions
+    }
+
+    /// The moves of the mating side worth trying with `moves` moves left: only checks can
+    /// mate at once.
+    fn mating_moves(&self, board: &Board, moves: u32) -> Vec<Move> {
+        let mut generator = MoveGenerator::new(board);
+        if moves == 1 {
+            generator.generate_checks();
+        } else {
+            generator.generate();
+        }
+        generator.moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::san::line_to_san;
+    use crate::problem::helpmate::*;
+
+    #[test]
+    pub fn helpmate_in_one() {
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R b - - 0 1").unwrap();
+        let mut solver = HelpmateSolver::new();
+        assert!(solver.helpmates_in(&board, 1));
+        let solution = solver.solve(&board, 1);
+        assert!(solution.is_sound());
+        assert!(!solution.has_duals());
+        assert_eq!(solution.to_text(&board), "1... Kb8\n  2. Rh8#\n");
+        assert!(solver.nodes() > 0);
+        assert_eq!(solver.solve(&board, 0).solutions, vec![]);
+    }
+
+    #[test]
+    pub fn helpmate_in_two() {
+        let board = board_from_fen("k7/8/8/1K6/8/8/8/7R b - - 0 1").unwrap();
+        let mut solver = HelpmateSolver::new();
+        assert!(!solver.helpmates_in(&board, 1));
+        let solution = solver.solve(&board, 2);
+        assert!(!solution.is_sound());
+        assert!(solution.has_duals());
+        let lines = solution.lines();
+        assert!(lines.len() > solution.solutions.len());
+        for line in lines.iter() {
+            assert_eq!(line.len(), 4);
+            let mut end = board;
+            for &m in line.iter() {
+                end.make_move(m);
+            }
+            assert!(is_mated(&end), "{}", line_to_san(&board, line));
+        }
+    }
+}
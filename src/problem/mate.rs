@@ -0,0 +1,253 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::{hash, hash_update};
+use crate::problem::{legal_moves, Defence, SolutionNode};
+use std::collections::HashMap;
+
+/// The solutions of a "mate in N" problem, direct or selfmate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MateSolution {
+    /// The number of moves of the problem.
+    pub moves: u32,
+    /// Every first move mating in at most `moves` moves, with its solution tree.
+    pub solutions: Vec<SolutionNode>,
+}
+
+impl MateSolution {
+    /// The first move of the solution, the first of them if the problem is cooked.
+    pub fn key_move(&self) -> Option<Move> {
+        self.solutions.first().map(|solution| solution.attack)
+    }
+
+    /// Whether the problem has a single solution.
+    pub fn is_sound(&self) -> bool {
+        self.solutions.len() == 1
+    }
+
+    /// The solutions besides the key move, which make the problem unsound.
+    pub fn cooks(&self) -> &[SolutionNode] {
+        self.solutions.get(1..).unwrap_or_default()
+    }
+
+    /// Whether a solution lets the solving side go on in more than one way after some defence.
+    pub fn has_duals(&self) -> bool {
+        self.solutions.iter().any(SolutionNode::has_duals)
+    }
+
+    /// Writes every solution tree from `board`.
+    pub fn to_text(&self, board: &Board) -> String {
+        self.solutions
+            .iter()
+            .map(|solution| solution.to_text(board, 1))
+            .collect()
+    }
+}
+
+/// Proves or refutes that the side to play mates in N moves, whatever the defence. The
+/// attacker tries every move, or only checks when `checks_only` is set, and always only checks
+/// for the mating move. The defender tries every move. Results are kept by position and
+/// number of moves, so transpositions are solved once.
+#[derive(Clone, Debug, Default)]
+pub struct MateSolver {
+    checks_only: bool,
+    table: HashMap<(u64, u32), bool>,
+    nodes: u64,
+}
+
+impl MateSolver {
+    pub fn new() -> MateSolver {
+        MateSolver::default()
+    }
+
+    /// Only looks for mates where every attacker move is a check, which is much faster.
+    pub fn set_checks_only(&mut self, checks_only: bool) {
+        self.checks_only = checks_only;
+        self.table.clear();
+    }
+
+    /// The positions searched since the solver was created.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Whether the side to play mates in at most `moves` moves.
+    pub fn mates_in(&mut self, board: &Board, moves: u32) -> bool {
+        moves > 0 && self.search(board, hash(board), moves)
+    }
+
+    /// Finds every first move mating in at most `moves` moves and its solution tree: each
+    /// defence, and every move still mating after it.
+    pub fn solve(&mut self, board: &Board, moves: u32) -> MateSolution {
+        let solutions = if moves == 0 {
+            Vec::new()
+        } else {
+            self.solutions(board, hash(board), moves)
+        };
+        MateSolution { moves, solutions }
+    }
+
+    fn search(&mut self, board: &Board, hash: u64, moves: u32) -> bool {
+        if let Some(&mates) = self.table.get(&(hash, moves)) {
+            return mates;
+        }
+        self.nodes += 1;
+        let mates = self
+            .attacks(board, moves)
+            .into_iter()
+            .any(|m| self.mates_after(board, hash, m, moves));
+        self.table.insert((hash, moves), mates);
+        mates
+    }
+
+    /// Whether `m` mates, or every reply to it allows a mate in `moves - 1` moves.
+    fn mates_after(&mut self, board: &Board, hash: u64, m: Move, moves: u32) -> bool {
+        let mut child = *board;
+        child.make_move(m);
+        let replies = legal_moves(&child);
+        if replies.is_empty() {
+            return !child.checkers().empty();
+        }
+        if moves == 1 {
+            return false;
+        }
+        let child_hash = hash_update(board, hash, m);
+        replies.into_iter().all(|reply| {
+            let mut next = child;
+            next.make_move(reply);
+            self.search(&next, hash_update(&child, child_hash, reply), moves - 1)
+        })
+    }
+
+    /// The trees of the moves mating in at most `moves` moves.
+    fn solutions(&mut self, board: &Board, hash: u64, moves: u32) -> Vec<SolutionNode> {
+        let mut solutions = Vec::new();
+        for m in self.attacks(board, moves) {
+            if self.mates_after(board, hash, m, moves) {
+                solutions.push(self.tree(board, hash, m, moves));
+            }
+        }
+        solutions
+    }
+
+    /// The solution tree of `m`, known to mate in `moves` moves.
+    fn tree(&mut self, board: &Board, hash: u64, m: Move, moves: u32) -> SolutionNode {
+        let mut child = *board;
+        child.make_move(m);
+        let child_hash = hash_update(board, hash, m);
+        let defences = legal_moves(&child)
+            .into_iter()
+            .map(|reply| {
+                let mut next = child;
+                next.make_move(reply);
+                let next_hash = hash_update(&child, child_hash, reply);
+                let continuations = self.solutions(&next, next_hash, moves - 1);
+                Defence {
+                    reply,
+                    continuations,
+                }
+            })
+            .collect();
+        SolutionNode {
+            attack: m,
+            defences,
+        }
+    }
+
+    /// The attacker moves worth trying with `moves` moves left: only checks can mate at once.
+    fn attacks(&self, board: &Board, moves: u32) -> Vec<Move> {
+        let mut generator = MoveGenerator::new(board);
+        if moves == 1 || self.checks_only {
+            generator.generate_checks();
+        } else {
+            generator.generate();
+        }
+        generator.moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::san::line_to_san;
+    use crate::problem::mate::*;
+
+    fn san(board: &Board, m: Option<Move>) -> String {
+        line_to_san(board, &m.into_iter().collect::<Vec<_>>())
+    }
+
+    #[test]
+    pub fn mate_in_one() {
+        let board = board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let mut solver = MateSolver::new();
+        assert!(solver.mates_in(&board, 1));
+        let solution = solver.solve(&board, 1);
+        assert!(solution.is_sound());
+        assert_eq!(san(&board, solution.key_move()), "Ra8#");
+        assert!(solution.solutions[0].defences.is_empty());
+        assert_eq!(solution.to_text(&board), "1. Ra8#\n");
+
+        // stalemate isn't mate: Kb6 and Ka6 stalemate, the queen has to wait along the rank
+        let board = board_from_fen("k7/2Q5/8/1K6/8/8/8/8 w - - 0 1").unwrap();
+        assert!(!solver.mates_in(&board, 1));
+        assert!(!solver.mates_in(&board, 2));
+        let solution = solver.solve(&board, 3);
+        let keys: Vec<String> = solution
+            .solutions
+            .iter()
+            .map(|solution| solution.attack.to_san(&board))
+            .collect();
+        assert_eq!(keys, ["Qd7", "Qe7", "Qf7", "Qg7", "Qh7"].map(String::from));
+        assert!(solver.nodes() > 0);
+    }
+
+    #[test]
+    pub fn quiet_key_move() {
+        // the queen is sacrificed to threaten mate on h7
+        let board =
+            board_from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1").unwrap();
+        let mut solver = MateSolver::new();
+        assert!(!solver.mates_in(&board, 1));
+        let solution = solver.solve(&board, 2);
+        assert!(solution.is_sound(), "{}", solution.to_text(&board));
+        assert_eq!(san(&board, solution.key_move()), "Qg6");
+        let key = &solution.solutions[0];
+        assert_eq!(key.length(), 2);
+        let mut after_key = board;
+        after_key.make_move(key.attack);
+        assert_eq!(key.defences.len(), legal_moves(&after_key).len());
+        assert!(key.defences.iter().all(|d| !d.continuations.is_empty()));
+
+        // the key move isn't a check
+        solver.set_checks_only(true);
+        assert!(!solver.mates_in(&board, 2));
+    }
+
+    #[test]
+    pub fn cooks() {
+        // the rook mates in two moves in more than one way
+        let board = board_from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        let mut solver = MateSolver::new();
+        let solution = solver.solve(&board, 2);
+        assert!(!solution.is_sound());
+        let keys = |solutions: &[SolutionNode]| -> Vec<String> {
+            solutions
+                .iter()
+                .map(|solution| solution.attack.to_san(&board))
+                .collect()
+        };
+        assert_eq!(keys(&solution.solutions), ["Kb6", "Kc7"]);
+        assert_eq!(keys(solution.cooks()), ["Kc7"]);
+        let text = solution.to_text(&board);
+        assert!(text.starts_with("1. "), "{}", text);
+        assert!(text.contains("\n  1... "));
+        assert!(text.contains("\n    2. R"));
+        assert!(text.trim_end().ends_with('#'));
+
+        // black to play
+        let board = board_from_fen("r5k1/8/8/8/8/8/5PPP/6K1 b - - 0 1").unwrap();
+        let solution = solver.solve(&board, 1);
+        assert_eq!(solution.to_text(&board), "1... Ra1#\n");
+        assert_eq!(solver.solve(&board, 0).solutions, vec![]);
+    }
+}
@@ -0,0 +1,94 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::Color::*;
+
+pub mod helpmate;
+pub mod mate;
+pub mod selfmate;
+
+/// A move of the side solving the problem, with every reply to it and the moves still solving
+/// the problem after each reply. A move ending the problem has no replies. In a helpmate both
+/// sides cooperate, so the replies are only the ones going on with the solution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolutionNode {
+    pub attack: Move,
+    pub defences: Vec<Defence>,
+}
+
+/// A reply to a move of a solution and the moves solving the problem after it, more than one
+/// being a dual.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Defence {
+    pub reply: Move,
+    pub continuations: Vec<SolutionNode>,
+}
+
+impl SolutionNode {
+    /// Writes the tree from `board` in standard algebraic notation, one move per line indented
+    /// by its depth, e.g. `1. Kb6`, `  1... Kb8`, `    2. Rh8#`.
+    pub fn to_text(&self, board: &Board, move_number: u32) -> String {
+        let mut text = String::new();
+        write_node(&mut text, self, board, move_number, 0);
+        text
+    }
+
+    /// The number of moves of the longest line of the tree.
+    pub fn length(&self) -> u32 {
+        1 + self
+            .defences
+            .iter()
+            .flat_map(|defence| defence.continuations.iter())
+            .map(SolutionNode::length)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Whether the solving side can go on in more than one way after some reply, a dual.
+    pub fn has_duals(&self) -> bool {
+        self.defences.iter().any(|defence| {
+            defence.continuations.len() > 1
+                || defence.continuations.iter().any(SolutionNode::has_duals)
+        })
+    }
+}
+
+/// Writes `node` played from `board` at full move `number`, then its replies and their
+/// continuations one level deeper.
+fn write_node(text: &mut String, node: &SolutionNode, board: &Board, number: u32, depth: usize) {
+    let number = write_move(text, board, node.attack, number, depth);
+    let mut child = *board;
+    child.make_move(node.attack);
+    for defence in node.defences.iter() {
+        let number = write_move(text, &child, defence.reply, number, depth + 1);
+        let mut next = child;
+        next.make_move(defence.reply);
+        for continuation in defence.continuations.iter() {
+            write_node(text, continuation, &next, number, depth + 2);
+        }
+    }
+}
+
+/// Writes a line with `m` played from `board` at full move `number`, e.g. `1. e4` or
+/// `1... e5`, and returns the full move number after it.
+fn write_move(text: &mut String, board: &Board, m: Move, number: u32, depth: usize) -> u32 {
+    let indent = "  ".repeat(depth);
+    if board.side_to_play == WHITE {
+        *text += &format!("{}{}. {}\n", indent, number, m.to_san(board));
+        number
+    } else {
+        *text += &format!("{}{}... {}\n", indent, number, m.to_san(board));
+        number + 1
+    }
+}
+
+/// Whether the side to play is checkmated.
+fn is_mated(board: &Board) -> bool {
+    legal_moves(board).is_empty() && !board.checkers().empty()
+}
+
+fn legal_moves(board: &Board) -> Vec<Move> {
+    let mut generator = MoveGenerator::new(board);
+    generator.generate();
+    generator.moves
+}
@@ -0,0 +1,171 @@
+use crate::core::board::Board;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::{hash, hash_update};
+use crate::problem::mate::MateSolution;
+use crate::problem::{is_mated, legal_moves, Defence, SolutionNode};
+use std::collections::HashMap;
+
+/// Proves or refutes that the side to play forces the other side to mate it in N moves
+/// ("s#N"), whatever the defence. The defender tries every move to avoid giving mate, and is
+/// forced when all its moves mate. Results are kept by position and number of moves.
+#[derive(Clone, Debug, Default)]
+pub struct SelfmateSolver {
+    table: HashMap<(u64, u32), bool>,
+    nodes: u64,
+}
+
+impl SelfmateSolver {
+    pub fn new() -> SelfmateSolver {
+        SelfmateSolver::default()
+    }
+
+    /// The positions searched since the solver was created.
+    pub fn nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    /// Whether the side to play forces a mate of itself in at most `moves` moves.
+    pub fn selfmates_in(&mut self, board: &Board, moves: u32) -> bool {
+        moves > 0 && self.search(board, hash(board), moves)
+    }
+
+    /// Finds every first move forcing a selfmate in at most `moves` moves and its solution
+    /// tree: each defence, and every move still forcing the mate after it. A defence giving
+    /// mate has no continuations.
+    pub fn solve(&mut self, board: &Board, moves: u32) -> MateSolution {
+        let solutions = if moves == 0 {
+            Vec::new()
+        } else {
+            self.solutions(board, hash(board), moves)
+        };
+        MateSolution { moves, solutions }
+    }
+
+    fn search(&mut self, board: &Board, hash: u64, moves: u32) -> bool {
+        if let Some(&forces) = self.table.get(&(hash, moves)) {
+            return forces;
+        }
+        self.nodes += 1;
+        let forces = legal_moves(board)
+            .into_iter()
+            .any(|m| self.forces_after(board, hash, m, moves));
+        self.table.insert((hash, moves), forces);
+        forces
+    }
+
+    /// Whether every reply to `m` mates, or allows a selfmate in `moves - 1` moves. The
+    /// defender must have a reply: mating or stalemating it ends the problem.
+    fn forces_after(&mut self, board: &Board, hash: u64, m: Move, moves: u32) -> bool {
+        let mut child = *board;
+        child.make_move(m);
+        let replies = legal_moves(&child);
+        if replies.is_empty() {
+            return false;
+        }
+        let child_hash = hash_update(board, hash, m);
+        replies.into_iter().all(|reply| {
+            let mut next = child;
+            next.make_move(reply);
+            is_mated(&next)
+                || (moves > 1
+                    && self.search(&next, hash_update(&child, child_hash, reply), moves - 1))
+        })
+    }
+
+    /// The trees of the moves forcing a selfmate in at most `moves` moves.
+    fn solutions(&mut self, board: &Board, hash: u64, moves: u32) -> Vec<SolutionNode> {
+        let mut solutions = Vec::new();
+        for m in legal_moves(board) {
+            if self.forces_after(board, hash, m, moves) {
+                solutions.push(self.tree(board, hash, m, moves));
+            }
+        }
+        solutions
+    }
+
+    /// The solution tree of `m`, known to force a selfmate in `moves` moves.
+    fn tree(&mut self, board: &Board, hash: u64, m: Move, moves: u32) -> SolutionNode {
+        let mut child = *board;
+        child.make_move(m);
+        let child_hash = hash_update(board, hash, m);
+        let defences = legal_moves(&child)
+            .into_iter()
+            .map(|reply| {
+                let mut next = child;
+                next.make_move(reply);
+                let continuations = if is_mated(&next) {
+                    Vec::new()
+                } else {
+                    let next_hash = hash_update(&child, child_hash, reply);
+                    self.solutions(&next, next_hash, moves - 1)
+                };
+                Defence {
+                    reply,
+                    continuations,
+                }
+            })
+            .collect();
+        SolutionNode {
+            attack: m,
+            defences,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::problem::selfmate::*;
+
+    #[test]
+    pub fn selfmate_in_one() {
+        // the rook takes the last flight squares, the pawn has to mate
+        let board = board_from_fen("8/8/8/7R/8/kp6/8/KB6 w - - 0 1").unwrap();
+        let mut solver = SelfmateSolver::new();
+        assert!(solver.selfmates_in(&board, 1));
+        let solution = solver.solve(&board, 1);
+        assert!(solution.is_sound(), "{}", solution.to_text(&board));
+        assert!(!solution.has_duals());
+        assert_eq!(solution.to_text(&board), "1. Rh4\n  1... b2#\n");
+        assert!(solver.nodes() > 0);
+
+        // a lone king never gives mate
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+        assert!(!solver.selfmates_in(&board, 1));
+        assert_eq!(solver.solve(&board, 0).solutions, vec![]);
+    }
+
+    #[test]
+    pub fn selfmate_in_two() {
+        // the rook has to stop the g-pawn, black has no other move than mating then
+        let board = board_from_fen("8/8/1B4p1/3R4/8/kp6/8/KB6 w - - 0 1").unwrap();
+        let mut solver = SelfmateSolver::new();
+        assert!(!solver.selfmates_in(&board, 1));
+        assert!(solver.selfmates_in(&board, 2));
+        let solution = solver.solve(&board, 2);
+        assert!(solution.is_sound());
+        assert!(!solution.has_duals());
+        assert_eq!(
+            solution.to_text(&board),
+            "1. Rd4\n  1... b2#\n  1... g5\n    2. Rg4\n      2... b2#\n"
+        );
+
+        // after 1. Re4 h3, the rook keeps the king off the fourth rank from any square along it
+        let board = board_from_fen("8/8/8/4R3/7p/kp6/7P/KB6 w - - 0 1").unwrap();
+        let solution = solver.solve(&board, 2);
+        assert!(solution.is_sound());
+        assert!(solution.has_duals());
+        let defence = &solution.solutions[0].defences[1];
+        assert_eq!(defence.continuations.len(), 5);
+    }
+
+    #[test]
+    pub fn stalemate_isnt_forced() {
+        // Qc7 leaves black without a move, which doesn't make it mate
+        let board = board_from_fen("k7/8/1K6/8/8/8/8/2Q5 w - - 0 1").unwrap();
+        let mut solver = SelfmateSolver::new();
+        let stalemate = Move::from_san("Qc7", &board).unwrap();
+        assert!(!solver.forces_after(&board, hash(&board), stalemate, 1));
+        assert!(!solver.selfmates_in(&board, 1));
+    }
+}
@@ -0,0 +1,67 @@
+use crate::core::fen::board_from_fen;
+use crate::eval::hand_crafted::HandCraftedEvaluator;
+use crate::search::searcher::Searcher;
+use crate::search::*;
+
+pub const BENCH_DEPTH: u32 = 7;
+
+/// Openings, middlegames and endgames, with tactics, checks and promotions.
+pub const BENCH_POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "2r3k1/pp3ppp/2n5/3p4/3P4/2N2N2/PP3PPP/2R3K1 b - - 0 20",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2K4/8 w - - 0 1",
+    "6k1/5p2/6p1/8/7p/8/6PP/6K1 b - - 0 1",
+];
+
+/// The outcome of a bench: the nodes searched are the same on every machine and change with
+/// any change of the search.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchResult {
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+impl BenchResult {
+    pub fn nps(&self) -> u64 {
+        (self.nodes as f64 / self.elapsed.as_secs_f64().max(1e-6)) as u64
+    }
+}
+
+/// Searches the bench positions to `depth` with a single thread, a fresh default size
+/// transposition table and the hand crafted evaluation.
+pub fn bench(parameters: SearchParameters, depth: u32) -> BenchResult {
+    let mut searcher = Searcher::new(HandCraftedEvaluator::default());
+    searcher.set_parameters(parameters);
+    let mut result = BenchResult {
+        nodes: 0,
+        elapsed: Duration::ZERO,
+    };
+    for fen in BENCH_POSITIONS {
+        let board = board_from_fen(fen).unwrap();
+        let search = searcher.search(&board, &SearchLimits::depth(depth));
+        result.nodes += search.nodes;
+        result.elapsed += search.elapsed;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::search::bench::*;
+
+    #[test]
+    pub fn bench_nodes() {
+        // update when the search changes on purpose
+        let result = bench(SearchParameters::default(), BENCH_DEPTH);
+        assert_eq!(result.nodes, 505940);
+        assert!(result.nps() > 0);
+
+        let plain = bench(SearchParameters::none(), 4);
+        let selective = bench(SearchParameters::default(), 4);
+        assert!(selective.nodes < plain.nodes);
+    }
+}
@@ -0,0 +1,206 @@
+use crate::core::r#move::Move;
+pub use crate::eval::{Evaluator, MaterialEvaluator};
+use std::time::Duration;
+
+pub mod bench;
+pub mod move_picker;
+pub mod searcher;
+pub mod time_manager;
+pub mod transposition_table;
+
+use crate::search::time_manager::Clock;
+
+/// Scores are in centipawns from the point of view of the side to play. Mate scores are
+/// `MATE - ply` for the side giving mate, so shorter mates score higher.
+pub const MATE: i32 = 32000;
+pub const INFINITY: i32 = MATE + 1;
+pub const MAX_PLY: usize = 128;
+
+pub const fn mate_in(ply: usize) -> i32 {
+    MATE - ply as i32
+}
+
+pub const fn mated_in(ply: usize) -> i32 {
+    -MATE + ply as i32
+}
+
+pub const fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - MAX_PLY as i32
+}
+
+/// Converts a mate score to the number of moves until mate, as reported by UCI `score mate`:
+/// positive when the side to play mates, negative when it gets mated.
+pub const fn mate_in_moves(score: i32) -> Option<i32> {
+    if !is_mate_score(score) {
+        None
+    } else if score > 0 {
+        Some((MATE - score + 1) / 2)
+    } else {
+        Some(-(MATE + score) / 2)
+    }
+}
+
+/// When to stop searching. The search stops at the first limit reached; with no limit at all,
+/// or with `infinite`, it runs until the stop flag is raised. With a clock, a `TimeManager`
+/// decides when to stop.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub clock: Option<Clock>,
+    pub infinite: bool,
+    /// Restricts the search to these root moves, or searches them all when empty. Illegal
+    /// moves are ignored.
+    pub search_moves: Vec<Move>,
+}
+
+impl SearchLimits {
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn movetime(movetime: Duration) -> SearchLimits {
+        SearchLimits {
+            movetime: Some(movetime),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn clock(clock: Clock) -> SearchLimits {
+        SearchLimits {
+            clock: Some(clock),
+            ..SearchLimits::default()
+        }
+    }
+
+    pub fn infinite() -> SearchLimits {
+        SearchLimits {
+            infinite: true,
+            ..SearchLimits::default()
+        }
+    }
+}
+
+/// The selective search techniques and their parameters. Each technique can be switched off,
+/// e.g. to measure what it brings: `SearchParameters::none()` is a plain alpha-beta search.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SearchParameters {
+    /// Skips a move and prunes if a reduced search still fails high. Not tried in check, or
+    /// without pieces, where zugzwang is common.
+    pub null_move: bool,
+    pub null_move_min_depth: i32,
+    /// The null move search is reduced by this many plies, plus one every 4 plies of depth.
+    pub null_move_reduction: i32,
+    /// From this depth, a null move cutoff is verified by a reduced search without null moves.
+    pub null_move_verification_depth: i32,
+    /// Searches late quiet moves with a reduced depth, and again at full depth if they raise
+    /// alpha.
+    pub late_move_reductions: bool,
+    pub lmr_min_depth: i32,
+    /// Moves searched at full depth before reducing.
+    pub lmr_min_moves: usize,
+    /// The reduction is `lmr_base + ln(depth) * ln(move number) / lmr_divisor`.
+    pub lmr_base: f64,
+    pub lmr_divisor: f64,
+    /// Skips quiet moves near the leaves when the static evaluation is too far below alpha.
+    pub futility: bool,
+    pub futility_max_depth: i32,
+    /// Margin in centipawns per ply of depth.
+    pub futility_margin: i32,
+    /// Prunes near the leaves when the static evaluation is far enough above beta.
+    pub reverse_futility: bool,
+    pub reverse_futility_max_depth: i32,
+    pub reverse_futility_margin: i32,
+    /// Drops to the quiescence search near the leaves when the static evaluation is far below
+    /// alpha and the quiescence search confirms it.
+    pub razoring: bool,
+    pub razoring_max_depth: i32,
+    pub razoring_margin: i32,
+    /// Searches moves giving check one ply deeper.
+    pub check_extensions: bool,
+    /// Searches each iteration with a window around the score of the previous one, widened
+    /// when the score falls outside.
+    pub aspiration_windows: bool,
+    pub aspiration_min_depth: u32,
+    /// Half width of the first window in centipawns, doubled on every failure.
+    pub aspiration_window: i32,
+}
+
+impl Default for SearchParameters {
+    fn default() -> Self {
+        SearchParameters {
+            null_move: true,
+            null_move_min_depth: 3,
+            null_move_reduction: 3,
+            null_move_verification_depth: 12,
+            late_move_reductions: true,
+            lmr_min_depth: 3,
+            lmr_min_moves: 3,
+            lmr_base: 0.75,
+            lmr_divisor: 2.25,
+            futility: true,
+            futility_max_depth: 3,
+            futility_margin: 150,
+            reverse_futility: true,
+            reverse_futility_max_depth: 6,
+            reverse_futility_margin: 100,
+            razoring: true,
+            razoring_max_depth: 2,
+            razoring_margin: 300,
+            check_extensions: true,
+            aspiration_windows: true,
+            aspiration_min_depth: 5,
+            aspiration_window: 25,
+        }
+    }
+}
+
+impl SearchParameters {
+    /// Every technique switched off.
+    pub fn none() -> SearchParameters {
+        SearchParameters {
+            null_move: false,
+            late_move_reductions: false,
+            futility: false,
+            reverse_futility: false,
+            razoring: false,
+            check_extensions: false,
+            aspiration_windows: false,
+            ..SearchParameters::default()
+        }
+    }
+}
+
+/// The outcome of the last completed iteration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: i32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub elapsed: Duration,
+    pub pv: Vec<Move>,
+    /// The best lines with different first moves, best first, as many as the MultiPV setting
+    /// of the searcher. The first one is `score` and `pv`.
+    pub lines: Vec<SearchLine>,
+}
+
+/// A principal variation and its score.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchLine {
+    pub score: i32,
+    pub pv: Vec<Move>,
+    /// The moves of `pv` in standard algebraic notation, separated by spaces.
+    pub san: String,
+}
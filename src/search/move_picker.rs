@@ -0,0 +1,324 @@
+use crate::core::bitboard::BitBoard;
+use crate::core::board::Board;
+use crate::core::r#move::{Move, PackedMove};
+use crate::core::see::SEE_VALUE;
+use crate::core::square::Square;
+use crate::core::Color;
+use crate::core::Piece::{self, *};
+use crate::search::MAX_PLY;
+
+/// History scores stay within `-MAX_HISTORY..=MAX_HISTORY`.
+pub const MAX_HISTORY: i32 = 16384;
+
+// scores of the move classes, in the order moves are picked
+const HASH_MOVE: i32 = i32::MAX;
+const GOOD_CAPTURE: i32 = 4 * MAX_HISTORY;
+const KILLER: i32 = 3 * MAX_HISTORY;
+const COUNTER_MOVE: i32 = 2 * MAX_HISTORY;
+const BAD_CAPTURE: i32 = -4 * MAX_HISTORY;
+
+/// Material won by a capture or promotion, before any recapture.
+pub fn capture_gain(board: &Board, m: Move) -> i32 {
+    match m {
+        Move::NormalMove { dest, .. } => board
+            .piece_at(BitBoard::from_square(dest))
+            .map_or(0, |piece| SEE_VALUE[piece]),
+        Move::Promotion { dest, piece, .. } => {
+            let captured = board.piece_at(BitBoard::from_square(dest));
+            captured.map_or(0, |piece| SEE_VALUE[piece]) + SEE_VALUE[piece] - SEE_VALUE[PAWN]
+        }
+        Move::EnPassant { .. } => SEE_VALUE[PAWN],
+        _ => 0,
+    }
+}
+
+/// Most valuable victim first, least valuable attacker first among equal victims.
+pub fn mvv_lva(board: &Board, m: Move) -> i32 {
+    let attacker = match m {
+        Move::NormalMove { piece, .. } => SEE_VALUE[piece],
+        _ => SEE_VALUE[PAWN],
+    };
+    capture_gain(board, m) * 8 - attacker / 100
+}
+
+/// The piece moved and the square it goes to, `None` for the null move.
+fn piece_and_dest(m: Move) -> Option<(Piece, Square)> {
+    let piece = match m {
+        Move::NormalMove { piece, .. } => piece,
+        Move::Promotion { .. } | Move::EnPassant { .. } => PAWN,
+        Move::NullMove => return None,
+        _ => KING,
+    };
+    Some((piece, PackedMove::from(m).dest()))
+}
+
+/// What the search learns about quiet moves: killer moves causing a cutoff at the same ply,
+/// the counter-move refuting the previous move, and the history of cutoffs of each piece
+/// moving to each square.
+#[derive(Clone, Debug)]
+pub struct OrderingHeuristics {
+    killers: [[Option<Move>; 2]; MAX_PLY],
+    /// Indexed by the piece and the destination of the previous move.
+    counter_moves: [[Option<Move>; 64]; 6],
+    /// Indexed by the side to play, the piece moved and its destination.
+    history: [[[i32; 64]; 6]; 2],
+}
+
+impl Default for OrderingHeuristics {
+    fn default() -> Self {
+        OrderingHeuristics {
+            killers: [[None; 2]; MAX_PLY],
+            counter_moves: [[None; 64]; 6],
+            history: [[[0; 64]; 6]; 2],
+        }
+    }
+}
+
+impl OrderingHeuristics {
+    pub fn new() -> OrderingHeuristics {
+        OrderingHeuristics::default()
+    }
+
+    /// Forgets the killers, which belong to the previous position, and halves the history.
+    pub fn new_search(&mut self) {
+        self.killers = [[None; 2]; MAX_PLY];
+        for score in self.history.iter_mut().flatten().flatten() {
+            *score /= 2;
+        }
+    }
+
+    pub fn killers(&self, ply: usize) -> [Option<Move>; 2] {
+        self.killers[ply]
+    }
+
+    pub fn counter_move(&self, previous: Option<Move>) -> Option<Move> {
+        let (piece, dest) = piece_and_dest(previous?)?;
+        self.counter_moves[piece][dest]
+    }
+
+    pub fn history(&self, color: Color, m: Move) -> i32 {
+        piece_and_dest(m).map_or(0, |(piece, dest)| self.history[color][piece][dest])
+    }
+
+    /// Records a cutoff by the quiet move `m` at `ply`, after `previous`. The history of `m`
+    /// gets a bonus growing with the depth, the quiet moves tried before it a malus.
+    pub fn update(
+        &mut self,
+        board: &Board,
+        m: Move,
+        ply: usize,
+        previous: Option<Move>,
+        depth: i32,
+        tried: &[Move],
+    ) {
+        if self.killers[ply][0] != Some(m) {
+            self.killers[ply][1] = self.killers[ply][0];
+            self.killers[ply][0] = Some(m);
+        }
+        if let Some((piece, dest)) = previous.and_then(piece_and_dest) {
+            self.counter_moves[piece][dest] = Some(m);
+        }
+        let bonus = (depth * depth).min(MAX_HISTORY);
+        self.add_history(board.side_to_play, m, bonus);
+        for &quiet in tried.iter().filter(|&&quiet| quiet != m) {
+            self.add_history(board.side_to_play, quiet, -bonus);
+        }
+    }
+
+    /// Adds `bonus` to the history, scaled down as the score gets closer to its bound.
+    fn add_history(&mut self, color: Color, m: Move, bonus: i32) {
+        if let Some((piece, dest)) = piece_and_dest(m) {
+            let score = &mut self.history[color][piece][dest];
+            *score += bonus - *score * bonus.abs() / MAX_HISTORY;
+        }
+    }
+}
+
+/// Returns moves best first: the hash move, captures and promotions by MVV-LVA, killer
+/// moves, the counter-move, quiet moves by history, and last the captures losing material by
+/// static exchange evaluation. Moves are scored up front but only sorted as they are picked,
+/// so that little work is wasted on a cutoff.
+#[derive(Clone, Debug)]
+pub struct MovePicker {
+    moves: Vec<Move>,
+    scores: Vec<i32>,
+    next: usize,
+}
+
+impl MovePicker {
+    /// Orders the legal `moves` of `board` at `ply`, reached by `previous`.
+    pub fn new(
+        board: &Board,
+        moves: Vec<Move>,
+        hash_move: Option<Move>,
+        heuristics: &OrderingHeuristics,
+        ply: usize,
+        previous: Option<Move>,
+    ) -> MovePicker {
+        let killers = heuristics.killers(ply);
+        let counter_move = heuristics.counter_move(previous);
+        let scores = moves
+            .iter()
+            .map(|&m| {
+                if Some(m) == hash_move {
+                    HASH_MOVE
+                } else if capture_gain(board, m) > 0 {
+                    let class = if board.see_ge(m, 0) {
+                        GOOD_CAPTURE
+                    } else {
+                        BAD_CAPTURE
+                    };
+                    class + mvv_lva(board, m)
+                } else if Some(m) == killers[0] {
+                    KILLER + 1
+                } else if Some(m) == killers[1] {
+                    KILLER
+                } else if Some(m) == counter_move {
+                    COUNTER_MOVE
+                } else {
+                    heuristics.history(board.side_to_play, m)
+                }
+            })
+            .collect();
+        MovePicker {
+            moves,
+            scores,
+            next: 0,
+        }
+    }
+
+    /// Orders captures by MVV-LVA alone, e.g. in a quiescence search.
+    pub fn captures(board: &Board, moves: Vec<Move>) -> MovePicker {
+        let scores = moves.iter().map(|&m| mvv_lva(board, m)).collect();
+        MovePicker {
+            moves,
+            scores,
+            next: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.moves.is_empty()
+    }
+}
+
+impl Iterator for MovePicker {
+    type Item = Move;
+
+    fn next(&mut self) -> Option<Move> {
+        if self.next == self.moves.len() {
+            return None;
+        }
+        // a selection sort, one move at a time
+        let mut best = self.next;
+        for i in self.next + 1..self.moves.len() {
+            if self.scores[i] > self.scores[best] {
+                best = i;
+            }
+        }
+        self.moves.swap(self.next, best);
+        self.scores.swap(self.next, best);
+        self.next += 1;
+        Some(self.moves[self.next - 1])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::move_generator::MoveGenerator;
+    use crate::search::move_picker::*;
+
+    fn legal_moves(board: &Board) -> Vec<Move> {
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        generator.moves
+    }
+
+    fn uci(board: &Board, m: &str) -> Move {
+        Move::from_uci(m, board).unwrap()
+    }
+
+    #[test]
+    pub fn picking_order() {
+        // the queen can be taken three ways, the rook defended by the queen by the knight, and
+        // the pawn defended by a pawn by the queen
+        let board = board_from_fen("7k/8/8/1r1q3p/4P1p1/2N5/8/3Q3K w - - 0 1").unwrap();
+        let moves = legal_moves(&board);
+        let mut heuristics = OrderingHeuristics::new();
+        let previous = uci(&board, "h1g2");
+        heuristics.update(&board, uci(&board, "h1h2"), 3, None, 4, &[]);
+        heuristics.update(&board, uci(&board, "d1d2"), 3, None, 4, &[]);
+        heuristics.update(&board, uci(&board, "d1a4"), 5, Some(previous), 4, &[]);
+        heuristics.update(&board, uci(&board, "c3e2"), 2, None, 8, &[]);
+
+        let picker = MovePicker::new(
+            &board,
+            moves.clone(),
+            Some(uci(&board, "h1g1")),
+            &heuristics,
+            3,
+            Some(previous),
+        );
+        assert_eq!(picker.len(), moves.len());
+        let picked: Vec<String> = picker.map(|m| m.to_string()).collect();
+        let expected = [
+            "h1g1", "e4d5", "c3d5", "d1d5", "c3b5", "d1d2", "h1h2", "d1a4", "c3e2",
+        ];
+        assert_eq!(picked[..9], expected.map(String::from));
+        // the queen takes the pawn and is taken back
+        assert_eq!(picked.last().unwrap(), "d1g4");
+        let mut sorted = picked.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted.len(), moves.len());
+
+        // without a previous move, the counter-move is ordered by its history only
+        let picker = MovePicker::new(&board, moves, None, &heuristics, 3, None);
+        let picked: Vec<String> = picker.map(|m| m.to_string()).collect();
+        assert_eq!(picked[6..8], ["c3e2", "d1a4"].map(String::from));
+
+        let board = board_from_fen("4k3/8/8/3q4/4P3/2N5/8/4K3 w - - 0 1").unwrap();
+        let mut generator = MoveGenerator::new(&board);
+        generator.generate_captures();
+        let picked: Vec<String> = MovePicker::captures(&board, generator.moves)
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(picked, ["e4d5", "c3d5"].map(String::from));
+    }
+
+    #[test]
+    pub fn history() {
+        let board = Board::from_initial_position();
+        let (e4, d4) = (uci(&board, "e2e4"), uci(&board, "d2d4"));
+        let mut heuristics = OrderingHeuristics::new();
+        heuristics.update(&board, e4, 0, None, 10, &[d4, e4]);
+        assert_eq!(heuristics.history(board.side_to_play, e4), 100);
+        assert_eq!(heuristics.history(board.side_to_play, d4), -100);
+        assert_eq!(heuristics.history(board.side_to_play.opposite(), e4), 0);
+        assert_eq!(heuristics.killers(0), [Some(e4), None]);
+        heuristics.update(&board, d4, 0, None, 1, &[]);
+        heuristics.update(&board, d4, 0, None, 1, &[]);
+        assert_eq!(heuristics.killers(0), [Some(d4), Some(e4)]);
+
+        // the history saturates
+        for _ in 0..1000 {
+            heuristics.update(&board, e4, 0, None, 200, &[]);
+        }
+        let score = heuristics.history(board.side_to_play, e4);
+        assert!(
+            score > MAX_HISTORY * 9 / 10 && score <= MAX_HISTORY,
+            "{}",
+            score
+        );
+
+        heuristics.new_search();
+        assert_eq!(heuristics.killers(0), [None, None]);
+        assert_eq!(heuristics.history(board.side_to_play, e4), score / 2);
+    }
+}
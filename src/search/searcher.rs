@@ -0,0 +1,1143 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::{Move, PackedMove};
+use crate::core::san::line_to_san;
+use crate::core::zobrist_hash::{hash, hash_update};
+use crate::core::Piece::*;
+use crate::search::move_picker::{capture_gain, MovePicker, OrderingHeuristics};
+use crate::search::time_manager::TimeManager;
+use crate::search::transposition_table::*;
+use crate::search::*;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Margin for positional gains when a capture can't bring the score back up to alpha.
+const DELTA_MARGIN: i32 = 200;
+
+/// Nodes searched between two checks of the clock and the stop flag, a power of two.
+const CHECK_INTERVAL: u64 = 1024;
+
+/// Depth staggering of the helper threads: helper `i` skips the depths where
+/// `(depth + SKIP_PHASE[i]) / SKIP_SIZE[i]` is odd, so that the threads spread over
+/// different depths.
+const SKIP_SIZE: [u32; 20] = [1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 4, 4, 4];
+const SKIP_PHASE: [u32; 20] = [0, 1, 0, 1, 2, 3, 0, 1, 2, 3, 4, 5, 0, 1, 2, 3, 4, 5, 6, 7];
+
+/// Principal variation search with iterative deepening. With several threads, helper
+/// searchers run the same search on their own copy of the board (lazy SMP): they only share
+/// the transposition table and the node count, the result is the one of the main thread.
+pub struct Searcher<E: Evaluator> {
+    evaluator: E,
+    tt: Arc<TranspositionTable>,
+    stop: Arc<AtomicBool>,
+    ponder: Arc<AtomicBool>,
+    helpers: Vec<Searcher<E>>,
+    /// 0 for the main thread, the index in the helpers plus one for the helpers.
+    thread_index: usize,
+    /// Nodes searched by all threads, updated every `CHECK_INTERVAL` nodes.
+    shared_nodes: Arc<AtomicU64>,
+    /// Nodes of this thread already added to `shared_nodes`.
+    flushed_nodes: u64,
+    parameters: SearchParameters,
+    /// Set while verifying a null move cutoff, when null moves aren't tried.
+    verifying: bool,
+    limits: SearchLimits,
+    start: Instant,
+    /// Set while the search ponders, when the time limits don't count yet.
+    pondering: bool,
+    /// When the time limits started counting: the start of the search, or the end of pondering.
+    time_start: Instant,
+    time: Option<TimeManager>,
+    nodes: u64,
+    aborted: bool,
+    /// Hashes of the positions since the last irreversible move, the game before the search
+    /// followed by the current search path.
+    hashes: Vec<u64>,
+    pv: Vec<[Move; MAX_PLY]>,
+    pv_length: [usize; MAX_PLY],
+    /// Principal variation of the last completed iteration, searched first in the next one.
+    previous_pv: Vec<Move>,
+    /// Number of lines searched, with different first moves.
+    multi_pv: usize,
+    /// The moves searched at the root: the search moves of the limits, without the first
+    /// moves of the lines already found in the iteration.
+    root_moves: Vec<Move>,
+    heuristics: Box<OrderingHeuristics>,
+    /// The move searched at each ply of the current path.
+    path: [Move; MAX_PLY],
+}
+
+impl<E: Evaluator + Clone + Send> Searcher<E> {
+    /// Sets the number of threads searching, the main one included. Helpers get a copy of the
+    /// evaluator.
+    pub fn set_threads(&mut self, threads: usize) {
+        let helpers = threads.max(1) - 1;
+        self.helpers.truncate(helpers);
+        while self.helpers.len() < helpers {
+            let mut helper =
+                Searcher::with_transposition_table(self.evaluator.clone(), self.tt.clone());
+            helper.thread_index = self.helpers.len() + 1;
+            helper.shared_nodes = self.shared_nodes.clone();
+            helper.parameters = self.parameters;
+            self.helpers.push(helper);
+        }
+    }
+}
+
+impl<E: Evaluator + Send> Searcher<E> {
+    pub fn new(evaluator: E) -> Searcher<E> {
+        Searcher::with_transposition_table(evaluator, Arc::new(TranspositionTable::default()))
+    }
+
+    /// Creates a searcher using `tt`, which may be shared with other searchers.
+    pub fn with_transposition_table(evaluator: E, tt: Arc<TranspositionTable>) -> Searcher<E> {
+        Searcher {
+            evaluator,
+            tt,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            helpers: Vec::new(),
+            thread_index: 0,
+            shared_nodes: Arc::new(AtomicU64::new(0)),
+            flushed_nodes: 0,
+            parameters: SearchParameters::default(),
+            verifying: false,
+            limits: SearchLimits::default(),
+            start: Instant::now(),
+            pondering: false,
+            time_start: Instant::now(),
+            time: None,
+            nodes: 0,
+            aborted: false,
+            hashes: Vec::new(),
+            pv: vec![[Move::NullMove; MAX_PLY]; MAX_PLY],
+            pv_length: [0; MAX_PLY],
+            previous_pv: Vec::new(),
+            multi_pv: 1,
+            root_moves: Vec::new(),
+            heuristics: Box::default(),
+            path: [Move::NullMove; MAX_PLY],
+        }
+    }
+
+    /// The flag stopping the search when raised, e.g. from another thread on UCI `stop`. It is
+    /// lowered when the search returns, so raising it before a search starts stops it at once.
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    /// The flag raised while the search ponders, e.g. from UCI `go ponder` to `ponderhit`. The
+    /// search keeps to its depth and node limits, but doesn't return before the flag is lowered
+    /// or the search is stopped. Its time limits only count from the moment the flag is lowered.
+    /// It is lowered when the search returns.
+    pub fn ponder_flag(&self) -> Arc<AtomicBool> {
+        self.ponder.clone()
+    }
+
+    pub fn evaluator(&mut self) -> &mut E {
+        &mut self.evaluator
+    }
+
+    pub fn transposition_table(&self) -> &Arc<TranspositionTable> {
+        &self.tt
+    }
+
+    /// Replaces the transposition table of every thread, e.g. to resize it.
+    pub fn set_transposition_table(&mut self, tt: Arc<TranspositionTable>) {
+        for helper in self.helpers.iter_mut() {
+            helper.set_transposition_table(tt.clone());
+        }
+        self.tt = tt;
+    }
+
+    pub fn parameters(&self) -> &SearchParameters {
+        &self.parameters
+    }
+
+    /// Sets the selective search techniques of every thread.
+    pub fn set_parameters(&mut self, parameters: SearchParameters) {
+        for helper in self.helpers.iter_mut() {
+            helper.set_parameters(parameters);
+        }
+        self.parameters = parameters;
+    }
+
+    pub fn multi_pv(&self) -> usize {
+        self.multi_pv
+    }
+
+    /// Sets the number of lines to search, each with a different first move. The lines after
+    /// the first are searched by the main thread only.
+    pub fn set_multi_pv(&mut self, lines: usize) {
+        self.multi_pv = lines.max(1);
+    }
+
+    /// The number of threads searching, the main one included.
+    pub fn threads(&self) -> usize {
+        self.helpers.len() + 1
+    }
+
+    /// Sets the hashes of the positions played before the one to search, oldest first, so
+    /// that repetitions of them are scored as draws.
+    pub fn set_game_history(&mut self, hashes: Vec<u64>) {
+        self.hashes = hashes;
+    }
+
+    pub fn search(&mut self, board: &Board, limits: &SearchLimits) -> SearchResult {
+        self.search_with_info(board, limits, |_| {})
+    }
+
+    /// Searches `board` until a limit is reached, calling `info` after every completed
+    /// iteration. The result is the one of the last completed iteration of the main thread,
+    /// with the nodes of every thread.
+    pub fn search_with_info(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        info: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.limits = limits.clone();
+        self.start = Instant::now();
+        self.pondering = self.ponder.load(Ordering::Relaxed);
+        self.time_start = self.start;
+        self.time = limits.clock.as_ref().map(TimeManager::new);
+        self.nodes = 0;
+        self.flushed_nodes = 0;
+        self.aborted = false;
+        self.previous_pv.clear();
+        self.heuristics.new_search();
+        if self.thread_index == 0 {
+            self.shared_nodes.store(0, Ordering::Relaxed);
+            self.tt.new_search();
+        }
+        let history_length = self.hashes.len();
+
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        let mut root_moves = generator.moves.clone();
+        root_moves.retain(|m| limits.search_moves.contains(m));
+        if root_moves.is_empty() {
+            root_moves = generator.moves;
+        }
+        if let Some(time) = self.time.as_mut() {
+            time.set_legal_moves(root_moves.len());
+        }
+        let pv: Vec<Move> = root_moves.first().copied().into_iter().collect();
+        let mut result = SearchResult {
+            best_move: root_moves.first().copied(),
+            score: 0,
+            depth: 0,
+            nodes: 0,
+            elapsed: self.start.elapsed(),
+            lines: vec![SearchLine {
+                score: 0,
+                san: line_to_san(board, &pv),
+                pv: pv.clone(),
+            }],
+            pv,
+        };
+        if root_moves.is_empty() {
+            result.score = if board.checkers().empty() {
+                0
+            } else {
+                mated_in(0)
+            };
+            result.lines.clear();
+            self.stop.store(false, Ordering::Relaxed);
+            self.ponder.store(false, Ordering::Relaxed);
+            return result;
+        }
+
+        let mut helpers = std::mem::take(&mut self.helpers);
+        let helper_stops: Vec<_> = helpers.iter().map(Searcher::stop_flag).collect();
+        // helpers search until the main thread is done, or the nodes of all threads reach the
+        // limit
+        let helper_limits = SearchLimits {
+            nodes: limits.nodes,
+            search_moves: limits.search_moves.clone(),
+            ..SearchLimits::infinite()
+        };
+        let result = std::thread::scope(|scope| {
+            for helper in helpers.iter_mut() {
+                helper.set_game_history(self.hashes.clone());
+                scope.spawn(|| helper.search(board, &helper_limits));
+            }
+            let result = self.iterative_deepening(board, limits, info, root_moves, result);
+            for stop in helper_stops.iter() {
+                stop.store(true, Ordering::Relaxed);
+            }
+            result
+        });
+        // a helper may have returned before its stop flag was raised
+        for stop in helper_stops.iter() {
+            stop.store(false, Ordering::Relaxed);
+        }
+        self.helpers = helpers;
+
+        let mut result = result;
+        result.nodes = self.total_nodes();
+        result.elapsed = self.start.elapsed();
+        self.hashes.truncate(history_length);
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder.store(false, Ordering::Relaxed);
+        result
+    }
+
+    fn iterative_deepening(
+        &mut self,
+        board: &Board,
+        limits: &SearchLimits,
+        mut info: impl FnMut(&SearchResult),
+        root_moves: Vec<Move>,
+        mut result: SearchResult,
+    ) -> SearchResult {
+        let max_depth = match limits.depth {
+            Some(depth) if !limits.infinite => depth.min(MAX_PLY as u32 - 1),
+            _ => MAX_PLY as u32 - 1,
+        };
+        let line_count = self.multi_pv.min(root_moves.len());
+        let root_hash = hash(board);
+        'iterations: for depth in 1..=max_depth {
+            if self.thread_index > 0 {
+                let i = (self.thread_index - 1) % SKIP_SIZE.len();
+                if (depth + SKIP_PHASE[i]) / SKIP_SIZE[i] % 2 == 1 {
+                    continue;
+                }
+            }
+            // each line is searched without the first moves of the lines found before it
+            let mut lines: Vec<SearchLine> = Vec::with_capacity(line_count);
+            for i in 0..line_count {
+                self.root_moves = root_moves
+                    .iter()
+                    .filter(|&&m| lines.iter().all(|line| line.pv[0] != m))
+                    .copied()
+                    .collect();
+                let previous = result.lines.get(i).filter(|_| result.depth > 0);
+                self.previous_pv = previous.map_or_else(Vec::new, |line| line.pv.clone());
+                let previous_score = previous.map(|line| line.score);
+                let score = self.aspiration_search(board, root_hash, depth, previous_score);
+                if self.aborted {
+                    break 'iterations;
+                }
+                let pv = self.pv[0][..self.pv_length[0]].to_vec();
+                lines.push(SearchLine {
+                    score,
+                    san: line_to_san(board, &pv),
+                    pv,
+                });
+            }
+            lines.sort_by_key(|line| -line.score);
+            let score = lines[0].score;
+            result = SearchResult {
+                best_move: Some(lines[0].pv[0]),
+                score,
+                depth,
+                nodes: self.total_nodes(),
+                elapsed: self.start.elapsed(),
+                pv: lines[0].pv.clone(),
+                lines,
+            };
+            info(&result);
+            self.check_ponderhit();
+            let pondering = self.pondering;
+            if let Some(time) = self.time.as_mut() {
+                time.update(result.pv[0], score);
+                if !pondering && time.stop_iterating() && !limits.infinite {
+                    break;
+                }
+            }
+            // a mate found within the depth can't get any shorter, the other lines can change
+            if is_mate_score(score)
+                && (MATE - score.abs()) as u32 <= depth
+                && line_count == 1
+                && !limits.infinite
+            {
+                break;
+            }
+        }
+
+        // with `infinite`, the caller expects the search to last until it is stopped, and a
+        // pondering search can't return before `ponderhit`
+        while ((limits.infinite && !self.aborted) || self.ponder.load(Ordering::Relaxed))
+            && !self.stop.load(Ordering::Relaxed)
+        {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        self.flush_nodes();
+        result
+    }
+
+    /// Searches the root with a window around the score of the previous iteration, widening it
+    /// on the side the score falls out of until it doesn't.
+    fn aspiration_search(
+        &mut self,
+        board: &Board,
+        hash: u64,
+        depth: u32,
+        previous: Option<i32>,
+    ) -> i32 {
+        let params = &self.parameters;
+        let previous = match previous {
+            Some(score)
+                if params.aspiration_windows
+                    && depth >= params.aspiration_min_depth
+                    && !is_mate_score(score) =>
+            {
+                score
+            }
+            _ => return self.negamax(board, hash, depth as i32, -INFINITY, INFINITY, 0),
+        };
+        let mut delta = params.aspiration_window;
+        let mut alpha = (previous - delta).max(-INFINITY);
+        let mut beta = (previous + delta).min(INFINITY);
+        loop {
+            let score = self.negamax(board, hash, depth as i32, alpha, beta, 0);
+            if self.aborted {
+                return score;
+            }
+            delta *= 2;
+            if score <= alpha {
+                alpha = (score - delta).max(-INFINITY);
+            } else if score >= beta {
+                beta = (score + delta).min(INFINITY);
+            } else {
+                return score;
+            }
+        }
+    }
+
+    fn negamax(
+        &mut self,
+        board: &Board,
+        hash: u64,
+        depth: i32,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+    ) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        if ply > 0 && self.is_draw(board, hash) {
+            return 0;
+        }
+        if depth <= 0 {
+            return self.quiescence(board, alpha, beta, ply, true);
+        }
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board);
+        }
+
+        let pv_node = beta - alpha > 1;
+        let tt_entry = self.tt.probe(hash);
+        if let Some(entry) = tt_entry {
+            let score = score_from_tt(entry.score, ply);
+            // the principal variation would be cut short by a cutoff
+            if !pv_node
+                && entry.depth as i32 >= depth
+                && match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                }
+            {
+                return score;
+            }
+        }
+
+        let in_check = !board.checkers().empty();
+        let previous = (ply > 0).then(|| self.path[ply - 1]);
+        let static_eval = if in_check {
+            -INFINITY
+        } else {
+            self.evaluator.evaluate(board)
+        };
+        let params = self.parameters;
+        if !pv_node && !in_check && !is_mate_score(beta) {
+            if params.reverse_futility
+                && depth <= params.reverse_futility_max_depth
+                && static_eval - params.reverse_futility_margin * depth >= beta
+            {
+                return static_eval;
+            }
+            if params.razoring
+                && depth <= params.razoring_max_depth
+                && static_eval + params.razoring_margin * depth < alpha
+            {
+                let score = self.quiescence(board, alpha, alpha + 1, ply, true);
+                if score <= alpha {
+                    return score;
+                }
+            }
+            if params.null_move
+                && !self.verifying
+                && depth >= params.null_move_min_depth
+                && static_eval >= beta
+                && previous != Some(Move::NullMove)
+                && has_pieces(board)
+            {
+                if let Some(score) = self.null_move(board, hash, depth, beta, ply) {
+                    return score;
+                }
+            }
+        }
+        let futile = params.futility
+            && !pv_node
+            && !in_check
+            && depth <= params.futility_max_depth
+            && !is_mate_score(alpha)
+            && static_eval + params.futility_margin * depth <= alpha;
+
+        let mut generator = MoveGenerator::new(board);
+        generator.generate();
+        if generator.moves.is_empty() {
+            return if in_check { mated_in(ply) } else { 0 };
+        }
+        // the best move among some root moves isn't the best move of the position
+        let mut restricted = false;
+        if ply == 0 {
+            let legal_moves = generator.moves.len();
+            generator.moves.retain(|m| self.root_moves.contains(m));
+            restricted = generator.moves.len() < legal_moves;
+        }
+        // the principal variation of the previous iteration comes before the hash move
+        let hash_move = tt_entry.and_then(|entry| entry.best_move.unpack(board));
+        let pv_move = self.previous_pv.get(ply).copied();
+        let first_move = pv_move
+            .filter(|m| generator.moves.contains(m))
+            .or(hash_move);
+        let moves = MovePicker::new(
+            board,
+            generator.moves,
+            first_move,
+            &self.heuristics,
+            ply,
+            previous,
+        );
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = PackedMove::NULL;
+        let mut quiets = Vec::new();
+        self.hashes.push(hash);
+        for (i, m) in moves.enumerate() {
+            let quiet = capture_gain(board, m) == 0;
+            let mut child = *board;
+            child.make_move(m);
+            let gives_check = !child.checkers().empty();
+            if futile && quiet && i > 0 && !gives_check {
+                continue;
+            }
+            let child_hash = hash_update(board, hash, m);
+            self.path[ply] = m;
+            let new_depth = if gives_check && params.check_extensions {
+                depth
+            } else {
+                depth - 1
+            };
+            // moves after the first are expected to fail low, which a null window proves faster,
+            // late quiet moves even more so, which a reduced depth proves faster still
+            let mut score;
+            if i == 0 {
+                score = -self.negamax(&child, child_hash, new_depth, -beta, -alpha, ply + 1);
+            } else {
+                let mut reduction = 0;
+                // a root move failing low at a reduced depth drops out of the iteration, with a
+                // tactic the full depth would have found
+                if params.late_move_reductions
+                    && depth >= params.lmr_min_depth
+                    && ply > 0
+                    && i >= params.lmr_min_moves
+                    && quiet
+                    && !in_check
+                    && !gives_check
+                {
+                    let r = params.lmr_base
+                        + (depth as f64).ln() * ((i + 1) as f64).ln() / params.lmr_divisor;
+                    reduction = (r as i32 - pv_node as i32).min(new_depth - 1).max(0);
+                }
+                score = -self.negamax(
+                    &child,
+                    child_hash,
+                    new_depth - reduction,
+                    -alpha - 1,
+                    -alpha,
+                    ply + 1,
+                );
+                if score > alpha && reduction > 0 && !self.aborted {
+                    score =
+                        -self.negamax(&child, child_hash, new_depth, -alpha - 1, -alpha, ply + 1);
+                }
+                if score > alpha && score < beta && !self.aborted {
+                    score = -self.negamax(&child, child_hash, new_depth, -beta, -alpha, ply + 1);
+                }
+            }
+            if self.aborted {
+                break;
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    best_move = PackedMove::from(m);
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        if quiet {
+                            self.heuristics
+                                .update(board, m, ply, previous, depth, &quiets);
+                        }
+                        break;
+                    }
+                }
+            }
+            if quiet {
+                quiets.push(m);
+            }
+        }
+        self.hashes.pop();
+
+        if !self.aborted && !restricted {
+            let bound = if best >= beta {
+                Bound::Lower
+            } else if best > original_alpha {
+                Bound::Exact
+            } else {
+                Bound::Upper
+            };
+            let depth = depth.min(u8::MAX as i32) as u8;
+            self.tt
+                .store(hash, best_move, score_to_tt(best, ply), depth, bound);
+        }
+        best
+    }
+
+    /// Lets the opponent play twice: if a reduced search still fails high, so would a real
+    /// move. Returns the score to prune with, if any.
+    fn null_move(
+        &mut self,
+        board: &Board,
+        hash: u64,
+        depth: i32,
+        beta: i32,
+        ply: usize,
+    ) -> Option<i32> {
+        let params = &self.parameters;
+        let reduction = params.null_move_reduction + depth / 4;
+        let verify = depth >= params.null_move_verification_depth;
+        let mut child = *board;
+        child.make_move(Move::NullMove);
+        let child_hash = hash_update(board, hash, Move::NullMove);
+        self.path[ply] = Move::NullMove;
+        self.hashes.push(hash);
+        let score = -self.negamax(
+            &child,
+            child_hash,
+            depth - 1 - reduction,
+            -beta,
+            -beta + 1,
+            ply + 1,
+        );
+        self.hashes.pop();
+        if self.aborted || score < beta {
+            return None;
+        }
+        // mates found after a null move aren't proven
+        let score = if is_mate_score(score) { beta } else { score };
+        if !verify {
+            return Some(score);
+        }
+        // in zugzwang, the null move is the best move and the verification fails low
+        self.verifying = true;
+        let verified = self.negamax(board, hash, depth - 1 - reduction, beta - 1, beta, ply);
+        self.verifying = false;
+        (!self.aborted && verified >= beta).then_some(score)
+    }
+
+    /// Searches captures and promotions, or every evasion when in check, until the position is
+    /// quiet. Captures that can't raise the score to alpha and captures losing material by
+    /// static exchange evaluation are skipped. With `checks`, quiet checks not losing material
+    /// are searched too: null move and razoring drop into quiescence, and would miss a mate
+    /// threat such as a quiet queen move mating next.
+    fn quiescence(
+        &mut self,
+        board: &Board,
+        mut alpha: i32,
+        beta: i32,
+        ply: usize,
+        checks: bool,
+    ) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.should_stop() {
+            return 0;
+        }
+        self.nodes += 1;
+        let in_check = !board.checkers().empty();
+        if ply >= MAX_PLY - 1 {
+            return self.evaluator.evaluate(board);
+        }
+
+        let mut best = -INFINITY;
+        let mut stand_pat = -INFINITY;
+        if !in_check {
+            stand_pat = self.evaluator.evaluate(board);
+            if stand_pat >= beta {
+                return stand_pat;
+            }
+            alpha = alpha.max(stand_pat);
+            best = stand_pat;
+        }
+
+        let mut generator = MoveGenerator::new(board);
+        generator.generate_captures();
+        if in_check && generator.moves.is_empty() {
+            return mated_in(ply);
+        }
+        let mut moves = generator.moves;
+        if checks && !in_check {
+            let mut generator = MoveGenerator::new(board);
+            generator.generate_checks();
+            let quiet_checks: Vec<Move> = generator
+                .moves
+                .into_iter()
+                .filter(|m| !moves.contains(m))
+                .collect();
+            moves.extend(quiet_checks);
+        }
+
+        for m in MovePicker::captures(board, moves) {
+            if !in_check {
+                let gain = capture_gain(board, m);
+                if gain > 0 && stand_pat + gain + DELTA_MARGIN <= alpha {
+                    continue;
+                }
+                if !board.see_ge(m, 0) {
+                    continue;
+                }
+            }
+            let mut child = *board;
+            child.make_move(m);
+            let score = -self.quiescence(&child, -beta, -alpha, ply + 1, false);
+            if self.aborted {
+                break;
+            }
+            if score > best {
+                best = score;
+                if score > alpha {
+                    alpha = score;
+                    self.update_pv(ply, m);
+                    if alpha >= beta {
+                        break;
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        self.pv[ply][ply] = m;
+        let child_length = self.pv_length[ply + 1].max(ply + 1);
+        for i in ply + 1..child_length {
+            self.pv[ply][i] = self.pv[ply + 1][i];
+        }
+        self.pv_length[ply] = child_length;
+    }
+
+    /// Fifty move rule or a repetition since the last irreversible move.
+    fn is_draw(&self, board: &Board, hash: u64) -> bool {
+        if board.half_move_counter >= 100 {
+            return true;
+        }
+        let reversible = (board.half_move_counter as usize).min(self.hashes.len());
+        self.hashes[self.hashes.len() - reversible..]
+            .iter()
+            .rev()
+            .skip(1)
+            .step_by(2)
+            .any(|&h| h == hash)
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.aborted {
+            return true;
+        }
+        if let Some(nodes) = self.limits.nodes {
+            if self.total_nodes() >= nodes {
+                self.aborted = true;
+            }
+        }
+        if self.nodes & (CHECK_INTERVAL - 1) == 0 {
+            self.flush_nodes();
+            if self.stop.load(Ordering::Relaxed) {
+                self.aborted = true;
+            }
+            self.check_ponderhit();
+            if !self.pondering {
+                if let Some(movetime) = self.limits.movetime {
+                    if self.time_start.elapsed() >= movetime {
+                        self.aborted = true;
+                    }
+                }
+                if self.time.as_ref().is_some_and(TimeManager::out_of_time) {
+                    self.aborted = true;
+                }
+            }
+        }
+        self.aborted
+    }
+
+    /// Starts counting the time once the ponder flag is lowered.
+    fn check_ponderhit(&mut self) {
+        if self.pondering && !self.ponder.load(Ordering::Relaxed) {
+            self.pondering = false;
+            self.time_start = Instant::now();
+            if let Some(time) = self.time.as_mut() {
+                time.restart();
+            }
+        }
+    }
+
+    /// Adds the nodes searched since the last call to the count shared by the threads.
+    fn flush_nodes(&mut self) {
+        self.shared_nodes
+            .fetch_add(self.nodes - self.flushed_nodes, Ordering::Relaxed);
+        self.flushed_nodes = self.nodes;
+    }
+
+    /// The nodes searched by all threads, as far as they are known.
+    fn total_nodes(&self) -> u64 {
+        self.shared_nodes.load(Ordering::Relaxed) + self.nodes - self.flushed_nodes
+    }
+}
+
+/// Whether the side to play has pieces other than pawns and the king.
+fn has_pieces(board: &Board) -> bool {
+    let pieces = board.piece_of_type(KNIGHT)
+        | board.piece_of_type(BISHOP)
+        | board.piece_of_type(ROOK)
+        | board.piece_of_type(QUEEN);
+    board.piece_of_color(board.side_to_play) * pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::epd::epd_from_str;
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::core::zobrist_hash::hash;
+    use crate::search::searcher::{Searcher, CHECK_INTERVAL};
+    use crate::search::*;
+    use std::sync::atomic::Ordering;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn search(fen: &str, depth: u32) -> SearchResult {
+        let board = board_from_fen(fen).unwrap();
+        Searcher::new(MaterialEvaluator).search(&board, &SearchLimits::depth(depth))
+    }
+
+    #[test]
+    pub fn mate_in_one() {
+        let result = search("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+        assert_eq!(result.score, mate_in(1));
+        assert_eq!(mate_in_moves(result.score), Some(1));
+    }
+
+    #[test]
+    pub fn mate_in_two() {
+        let fen = "k7/8/2K5/8/8/8/8/7R w - - 0 1";
+        let result = search(fen, 4);
+        assert_eq!(mate_in_moves(result.score), Some(2), "{:?}", result);
+        assert_eq!(result.pv.len(), 3);
+        let mut board = board_from_fen(fen).unwrap();
+        for &m in result.pv.iter() {
+            assert_eq!(Move::from_uci(&m.to_string(), &board), Some(m));
+            board.make_move(m);
+        }
+        assert!(!board.checkers().empty());
+    }
+
+    #[test]
+    pub fn getting_mated() {
+        let result = search("6k1/8/8/8/8/8/r7/1r5K w - - 0 1", 3);
+        assert_eq!(result.score, mated_in(0));
+        let result = search("6k1/8/8/8/8/8/r7/7K w - - 0 1", 3);
+        assert_eq!(mate_in_moves(result.score), None);
+        let result = search("1r5k/8/8/8/8/8/r7/6K1 w - - 0 1", 3);
+        assert_eq!(mate_in_moves(result.score), Some(-1));
+    }
+
+    #[test]
+    pub fn stalemate() {
+        let result = search("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+        assert_eq!(result.best_move, None);
+        assert_eq!(result.score, 0);
+    }
+
+    #[test]
+    pub fn wins_material() {
+        let result = search("1k6/pp6/8/3q4/8/8/3R4/3RK3 w - - 0 1", 2);
+        assert_eq!(result.best_move.unwrap().to_string(), "d2d5");
+        assert_eq!(result.depth, 2);
+    }
+
+    #[test]
+    pub fn quiescence_sees_recaptures() {
+        // at depth 1 the pawn looks free, but it is defended
+        let result = search("4k3/8/3p4/4p3/8/8/8/K3Q3 w - - 0 1", 1);
+        assert_ne!(result.best_move.unwrap().to_string(), "e1e5");
+        assert!(result.score > 700, "{:?}", result);
+    }
+
+    #[test]
+    pub fn multi_pv() {
+        let board = board_from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1").unwrap();
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        searcher.set_multi_pv(3);
+        let result = searcher.search(&board, &SearchLimits::depth(4));
+        assert_eq!(result.lines.len(), 3);
+        assert_eq!(result.lines[0].san, "Ra8#");
+        assert_eq!(result.lines[0].score, mate_in(1));
+        assert_eq!(
+            (result.score, &result.pv),
+            (mate_in(1), &result.lines[0].pv)
+        );
+        assert!(result.lines[1].score < mate_in(1));
+        assert!(result.lines[1].score >= result.lines[2].score);
+        assert_ne!(result.lines[1].pv[0], result.lines[2].pv[0]);
+        assert!(result.lines[1]
+            .san
+            .starts_with(&result.lines[1].pv[0].to_san(&board)));
+
+        // the mate isn't among the search moves, and there are only two of them
+        let h3 = Move::from_uci("h2h3", &board).unwrap();
+        let a2 = Move::from_uci("a1a2", &board).unwrap();
+        let limits = SearchLimits {
+            depth: Some(3),
+            search_moves: vec![h3, a2],
+            ..SearchLimits::default()
+        };
+        let result = searcher.search(&board, &limits);
+        assert_eq!(result.lines.len(), 2);
+        assert!(result
+            .lines
+            .iter()
+            .all(|line| [h3, a2].contains(&line.pv[0])));
+        searcher.set_multi_pv(1);
+        let result = searcher.search(&board, &limits);
+        assert_eq!(result.lines.len(), 1);
+        assert!(!is_mate_score(result.score));
+
+        // the table of a restricted search doesn't hide the mate
+        let result = searcher.search(&board, &SearchLimits::depth(3));
+        assert_eq!(result.best_move.unwrap().to_string(), "a1a8");
+    }
+
+    #[test]
+    pub fn selective_search() {
+        let none = SearchParameters::none();
+        let variants = [
+            SearchParameters::default(),
+            none,
+            SearchParameters {
+                null_move: true,
+                null_move_verification_depth: 1,
+                ..none
+            },
+            SearchParameters {
+                late_move_reductions: true,
+                ..none
+            },
+            // reductions at depth 1 leave no depth to reduce
+            SearchParameters {
+                late_move_reductions: true,
+                lmr_min_depth: 1,
+                lmr_min_moves: 1,
+                ..none
+            },
+            SearchParameters {
+                futility: true,
+                reverse_futility: true,
+                razoring: true,
+                ..none
+            },
+            SearchParameters {
+                check_extensions: true,
+                ..none
+            },
+            SearchParameters {
+                aspiration_windows: true,
+                aspiration_min_depth: 1,
+                ..none
+            },
+        ];
+        let board = board_from_fen("k7/8/2K5/8/8/8/8/7R w - - 0 1").unwrap();
+        for parameters in variants {
+            let mut searcher = Searcher::new(MaterialEvaluator);
+            searcher.set_parameters(parameters);
+            let result = searcher.search(&board, &SearchLimits::depth(6));
+            assert_eq!(mate_in_moves(result.score), Some(2), "{:?}", parameters);
+            // and a position with every piece, where most of the pruning happens
+            let result = searcher.search(&Board::from_initial_position(), &SearchLimits::depth(4));
+            assert!(result.best_move.is_some());
+        }
+    }
+
+    #[test]
+    pub fn tactical_epd_positions() {
+        let suite = [
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+            "r1bq2rk/pp3pbp/2p1p1pQ/7P/3P4/2PB1N2/PP3PPR/2KR4 w - - bm Qxh7+; id \"WAC.004\";",
+            "5k2/6pp/p1qN4/1p1p4/3P4/2PKP2Q/PP3r2/3R4 b - - bm Qc4+; id \"WAC.005\";",
+            "7k/p7/1R5K/6r1/6p1/6P1/8/8 w - - bm Rb7; id \"WAC.006\";",
+            "rnbqkb1r/pppp1ppp/8/4P3/6n1/7P/PPPNPPP1/R1BQKBNR b KQkq - bm Ne3; id \"WAC.007\";",
+            "r4q1k/p2bR1rp/2p2Q1N/5p2/5p2/2P5/PP3PPP/R5K1 w - - bm Rf7; id \"WAC.008\";",
+            "3q1rk1/p4pp1/2pb3p/3p4/6Pr/1PNQ4/P1PB1PP1/4RRK1 b - - bm Bh2+; id \"WAC.009\";",
+            "2br2k1/2q3rn/p2NppQ1/2p1P3/Pp5R/4P3/1P3PPP/3R2K1 w - - bm Rxh7; id \"WAC.010\";",
+        ];
+        for line in suite {
+            let epd = epd_from_str(line).unwrap();
+            let mut searcher = Searcher::new(MaterialEvaluator);
+            let result = searcher.search(&epd.board, &SearchLimits::depth(5));
+            let best_move = result.best_move.unwrap();
+            assert!(
+                epd.best_moves().unwrap().contains(&best_move),
+                "{}: {}",
+                epd.id().unwrap(),
+                best_move.to_san(&epd.board)
+            );
+        }
+    }
+
+    #[test]
+    pub fn draws() {
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        let mut board = Board::from_initial_position();
+        let mut hashes = vec![];
+        for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+            hashes.push(hash(&board));
+            board.make_move(Move::from_uci(uci, &board).unwrap());
+        }
+        searcher.set_game_history(hashes);
+        assert!(searcher.is_draw(&board, hash(&board)));
+        searcher.set_game_history(vec![]);
+        assert!(!searcher.is_draw(&board, hash(&board)));
+
+        let board = board_from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 100 80").unwrap();
+        assert!(searcher.is_draw(&board, hash(&board)));
+    }
+
+    #[test]
+    pub fn node_limit() {
+        let board = Board::from_initial_position();
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        let result = searcher.search(&board, &SearchLimits::nodes(5000));
+        assert!(result.nodes <= 5000);
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    pub fn movetime_limit() {
+        let board = Board::from_initial_position();
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        let start = Instant::now();
+        let result = searcher.search(&board, &SearchLimits::movetime(Duration::from_millis(50)));
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(result.best_move.is_some());
+    }
+
+    #[test]
+    pub fn infinite_until_stopped() {
+        let board = Board::from_initial_position();
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        let stop = searcher.stop_flag();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let result = searcher.search(&board, &SearchLimits::infinite());
+        stopper.join().unwrap();
+        assert!(result.best_move.is_some());
+        assert!(result.depth >= 1);
+    }
+
+    #[test]
+    pub fn lazy_smp() {
+        let board = Board::from_initial_position();
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        searcher.set_threads(4);
+        assert_eq!(searcher.threads(), 4);
+        let result = searcher.search(&board, &SearchLimits::depth(5));
+        assert_eq!(result.depth, 5);
+        assert!(result.best_move.is_some());
+
+        // the node limit counts the nodes of every thread, up to those the other threads
+        // haven't reported yet
+        let result = searcher.search(&board, &SearchLimits::nodes(50000));
+        assert!(result.nodes >= 50000);
+        assert!(
+            result.nodes <= 50000 + 3 * CHECK_INTERVAL,
+            "{}",
+            result.nodes
+        );
+
+        // every thread stops promptly
+        let stop = searcher.stop_flag();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            stop.store(true, Ordering::Relaxed);
+        });
+        let start = Instant::now();
+        let result = searcher.search(&board, &SearchLimits::infinite());
+        stopper.join().unwrap();
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(result.depth >= 1);
+
+        searcher.set_threads(1);
+        assert_eq!(searcher.threads(), 1);
+    }
+
+    #[test]
+    pub fn pruning_keeps_quiet_mate_threats() {
+        // WAC.001: Qg6 threatens Qh7#, which null move and razoring only see through the quiet
+        // checks of quiescence, and only if late move reductions don't skip it at the root
+        let board =
+            board_from_fen("2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 0 1").unwrap();
+        let none = SearchParameters::none();
+        let variants = [
+            SearchParameters::default(),
+            SearchParameters {
+                null_move: true,
+                late_move_reductions: true,
+                ..none
+            },
+            SearchParameters {
+                null_move: true,
+                razoring: true,
+                ..none
+            },
+            SearchParameters {
+                late_move_reductions: true,
+                razoring: true,
+                ..none
+            },
+        ];
+        for parameters in variants {
+            let mut searcher = Searcher::new(MaterialEvaluator);
+            searcher.set_parameters(parameters);
+            let result = searcher.search(&board, &SearchLimits::depth(5));
+            assert_eq!(
+                result.best_move.unwrap().to_san(&board),
+                "Qg6",
+                "{:?}",
+                parameters
+            );
+            assert!(is_mate_score(result.score), "{:?}", parameters);
+        }
+    }
+}
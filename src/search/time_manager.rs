@@ -0,0 +1,207 @@
+use crate::core::r#move::Move;
+use std::time::{Duration, Instant};
+
+/// Number of moves the remaining time is split over when the number of moves to the next time
+/// control isn't known.
+pub const DEFAULT_MOVES_TO_GO: u32 = 30;
+
+/// The hard deadline is at most this many times the soft one.
+const HARD_RATIO: u32 = 4;
+
+/// The soft deadline is never extended beyond this many times its initial value.
+const MAX_EXTENSION: f64 = 3.0;
+
+/// The clock of the side to play, as sent by the GUI.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// Moves to play until the next time control, if any.
+    pub moves_to_go: Option<u32>,
+    /// Time kept in reserve for the communication with the GUI.
+    pub move_overhead: Duration,
+}
+
+/// Turns a clock into deadlines for a search. Iterative deepening starts no new iteration
+/// after the soft deadline, and the search is aborted at the hard one. The soft deadline is
+/// pushed back while the best move changes or the score drops.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    /// Decaying count of the best move changes between iterations.
+    instability: f64,
+    /// Centipawns lost since the first iteration, at most 100.
+    score_drop: i32,
+    first_score: Option<i32>,
+    best_move: Option<Move>,
+    single_legal_move: bool,
+}
+
+impl TimeManager {
+    /// Starts the clock for a move. The soft limit is a share of the remaining time plus most of
+    /// the increment, never more than the time left once the overhead is kept in reserve.
+    pub fn new(clock: &Clock) -> TimeManager {
+        let available = clock.remaining.saturating_sub(clock.move_overhead);
+        let moves = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+        let minimum = Duration::from_millis(1);
+        let soft = (clock.remaining / moves + clock.increment * 3 / 4)
+            .min(available)
+            .max(minimum);
+        let hard = (soft * HARD_RATIO).min(available).max(soft);
+        TimeManager {
+            start: Instant::now(),
+            soft,
+            hard,
+            instability: 0.0,
+            score_drop: 0,
+            first_score: None,
+            best_move: None,
+            single_legal_move: false,
+        }
+    }
+
+    /// Starts the clock again, e.g. once the opponent played the move pondered on.
+    pub fn restart(&mut self) {
+        self.start = Instant::now();
+    }
+
+    /// The time since the clock was started.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// The time after which no new iteration is started, including extensions.
+    pub fn soft_limit(&self) -> Duration {
+        let extension = (1.0 + self.instability) * (1.0 + self.score_drop as f64 / 100.0);
+        self.soft
+            .mul_f64(extension.min(MAX_EXTENSION))
+            .min(self.hard)
+    }
+
+    /// The time after which the search is aborted.
+    pub fn hard_limit(&self) -> Duration {
+        self.hard
+    }
+
+    pub fn soft_deadline(&self) -> Instant {
+        self.start + self.soft_limit()
+    }
+
+    pub fn hard_deadline(&self) -> Instant {
+        self.start + self.hard
+    }
+
+    /// Sets the number of legal moves at the root: with a single one, there is nothing to
+    /// think about.
+    pub fn set_legal_moves(&mut self, count: usize) {
+        self.single_legal_move = count == 1;
+    }
+
+    /// Records the result of a completed iteration.
+    pub fn update(&mut self, best_move: Move, score: i32) {
+        self.instability /= 2.0;
+        if self.best_move.is_some_and(|previous| previous != best_move) {
+            self.instability += 1.0;
+        }
+        self.best_move = Some(best_move);
+        let first_score = *self.first_score.get_or_insert(score);
+        self.score_drop = (first_score - score).clamp(0, 100);
+    }
+
+    /// Whether to play the best move found instead of starting a new iteration.
+    pub fn stop_iterating(&self) -> bool {
+        self.single_legal_move || self.elapsed() >= self.soft_limit()
+    }
+
+    /// Whether the running iteration must be aborted.
+    pub fn out_of_time(&self) -> bool {
+        self.elapsed() >= self.hard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::search::searcher::Searcher;
+    use crate::search::time_manager::*;
+    use crate::search::*;
+
+    fn clock(remaining: u64, increment: u64, moves_to_go: Option<u32>) -> Clock {
+        Clock {
+            remaining: Duration::from_millis(remaining),
+            increment: Duration::from_millis(increment),
+            moves_to_go,
+            move_overhead: Duration::from_millis(30),
+        }
+    }
+
+    #[test]
+    pub fn deadlines() {
+        let time = TimeManager::new(&clock(60000, 1000, Some(20)));
+        assert_eq!(time.soft_limit(), Duration::from_millis(3750));
+        assert_eq!(time.hard_limit(), Duration::from_millis(15000));
+        assert!(time.soft_deadline() < time.hard_deadline());
+
+        // most of the time left for the last move before the time control
+        let time = TimeManager::new(&clock(1000, 0, Some(1)));
+        assert_eq!(time.soft_limit(), Duration::from_millis(970));
+        assert_eq!(time.hard_limit(), Duration::from_millis(970));
+
+        // never more than the time left
+        let time = TimeManager::new(&clock(40, 1000, None));
+        assert_eq!(time.soft_limit(), Duration::from_millis(10));
+        let time = TimeManager::new(&clock(10, 0, None));
+        assert_eq!(time.soft_limit(), Duration::from_millis(1));
+    }
+
+    #[test]
+    pub fn extensions() {
+        let board = Board::from_initial_position();
+        let e4 = Move::from_uci("e2e4", &board).unwrap();
+        let d4 = Move::from_uci("d2d4", &board).unwrap();
+        let mut time = TimeManager::new(&clock(60000, 0, None));
+        let base = time.soft_limit();
+        time.update(e4, 30);
+        time.update(e4, 35);
+        assert_eq!(time.soft_limit(), base);
+
+        // the best move changes
+        time.update(d4, 30);
+        assert_eq!(time.soft_limit(), base * 2);
+        time.update(d4, 30);
+        assert_eq!(time.soft_limit(), base * 3 / 2);
+
+        // the score drops by half a pawn, on top of the instability
+        time.update(d4, -20);
+        assert_eq!(time.soft_limit(), base.mul_f64(1.25 * 1.5));
+        time.update(e4, -500);
+        assert_eq!(time.soft_limit(), base * 3);
+        assert!(time.soft_limit() <= time.hard_limit());
+
+        let mut time = TimeManager::new(&clock(60000, 0, None));
+        assert!(!time.stop_iterating());
+        time.set_legal_moves(1);
+        assert!(time.stop_iterating());
+    }
+
+    #[test]
+    pub fn timed_search() {
+        let mut searcher = Searcher::new(MaterialEvaluator);
+        let limits = SearchLimits::clock(clock(2000, 0, None));
+        let board = Board::from_initial_position();
+        let start = std::time::Instant::now();
+        let result = searcher.search(&board, &limits);
+        assert!(result.depth > 1);
+        assert!(start.elapsed() < Duration::from_millis(500));
+
+        // a single legal move is played after the first iteration
+        let board = board_from_fen("k7/8/8/8/8/8/1q6/K7 w - - 0 1").unwrap();
+        let result = searcher.search(&board, &SearchLimits::clock(clock(60000, 0, None)));
+        assert_eq!(result.depth, 1);
+        assert_eq!(result.best_move.unwrap().to_string(), "a1b2");
+    }
+}
@@ -0,0 +1,365 @@
+use crate::core::r#move::PackedMove;
+use crate::search::*;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high: the score is a lower bound.
+    Lower,
+    /// The search failed low: the score is an upper bound.
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TtEntry {
+    pub best_move: PackedMove,
+    /// Mate scores are relative to the position stored, see `score_to_tt`.
+    pub score: i32,
+    pub depth: u8,
+    pub bound: Bound,
+    pub age: u8,
+}
+
+impl TtEntry {
+    // move in bits 0-15, score in bits 16-31, depth in bits 32-39, bound in bits 40-41 and age
+    // in bits 42-47. Zero is an empty slot.
+    fn pack(self) -> u64 {
+        self.best_move.0 as u64
+            | (self.score as i16 as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | (self.bound as u64 + 1) << 40
+            | ((self.age & AGE_MASK) as u64) << 42
+    }
+
+    fn unpack(data: u64) -> TtEntry {
+        TtEntry {
+            best_move: PackedMove(data as u16),
+            score: (data >> 16) as u16 as i16 as i32,
+            depth: (data >> 32) as u8,
+            bound: match (data >> 40) & 3 {
+                1 => Bound::Exact,
+                2 => Bound::Lower,
+                _ => Bound::Upper,
+            },
+            age: (data >> 42) as u8 & AGE_MASK,
+        }
+    }
+}
+
+const AGE_MASK: u8 = 0x3F;
+const ENTRIES_PER_BUCKET: usize = 4;
+
+/// The key is stored XOR-ed with the data, so an entry torn by two threads writing at the same
+/// time fails the key check instead of returning the data of another position.
+#[derive(Default)]
+struct Entry {
+    key_xor_data: AtomicU64,
+    data: AtomicU64,
+}
+
+/// A cache line of entries. The first ones are replaced by deeper or newer searches, the last
+/// one is always replaced.
+#[derive(Default)]
+#[repr(align(64))]
+struct Bucket([Entry; ENTRIES_PER_BUCKET]);
+
+/// Hash table of search results keyed by Zobrist hash, shared by the search threads.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    pub const DEFAULT_SIZE_MB: usize = 16;
+
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let mut table = TranspositionTable {
+            buckets: Vec::new(),
+            age: AtomicU8::new(0),
+        };
+        table.resize(size_mb);
+        table
+    }
+
+    /// Resizes the table to `size_mb` megabytes, rounded down to a power of two number of
+    /// buckets, and clears it.
+    pub fn resize(&mut self, size_mb: usize) {
+        let buckets = (size_mb.max(1) << 20) / std::mem::size_of::<Bucket>();
+        let buckets = 1 << (usize::BITS - 1 - buckets.leading_zeros());
+        self.buckets = Vec::with_capacity(buckets);
+        self.buckets.resize_with(buckets, Bucket::default);
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    pub fn size_mb(&self) -> usize {
+        (self.buckets.len() * std::mem::size_of::<Bucket>()) >> 20
+    }
+
+    pub fn clear(&mut self) {
+        for bucket in self.buckets.iter_mut() {
+            *bucket = Bucket::default();
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Starts a new search, making the entries of the previous ones easier to replace.
+    pub fn new_search(&self) {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store((age + 1) & AGE_MASK, Ordering::Relaxed);
+    }
+
+    fn bucket(&self, key: u64) -> &Bucket {
+        &self.buckets[key as usize & (self.buckets.len() - 1)]
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TtEntry> {
+        self.bucket(key).0.iter().find_map(|entry| {
+            let data = entry.data.load(Ordering::Relaxed);
+            let key_xor_data = entry.key_xor_data.load(Ordering::Relaxed);
+            (data != 0 && key_xor_data ^ data == key).then(|| TtEntry::unpack(data))
+        })
+    }
+
+    pub fn store(&self, key: u64, best_move: PackedMove, score: i32, depth: u8, bound: Bound) {
+        let age = self.age.load(Ordering::Relaxed);
+        let mut entry = TtEntry {
+            best_move,
+            score,
+            depth,
+            bound,
+            age,
+        };
+        let slots = &self.bucket(key).0;
+
+        let existing = slots.iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            (data != 0 && slot.key_xor_data.load(Ordering::Relaxed) ^ data == key)
+                .then(|| (slot, TtEntry::unpack(data)))
+        });
+        let target = match existing {
+            Some((slot, old)) => {
+                // keep the move of a previous search that didn't find one, and deeper results
+                // of the same search unless the new one is exact
+                if best_move == PackedMove::NULL {
+                    entry.best_move = old.best_move;
+                }
+                if old.age == age && old.depth > depth && bound != Bound::Exact {
+                    return;
+                }
+                slot
+            }
+            None => {
+                // the least valuable depth-preferred slot if the new result is worth at least as
+                // much, the always-replace slot otherwise
+                let (slot, worth) = slots[..ENTRIES_PER_BUCKET - 1]
+                    .iter()
+                    .map(|slot| {
+                        let data = slot.data.load(Ordering::Relaxed);
+                        if data == 0 {
+                            return (slot, i32::MIN);
+                        }
+                        let old = TtEntry::unpack(data);
+                        let staleness = age.wrapping_sub(old.age) & AGE_MASK;
+                        (slot, old.depth as i32 - 8 * staleness as i32)
+                    })
+                    .min_by_key(|&(_, worth)| worth)
+                    .unwrap();
+                if worth <= depth as i32 {
+                    slot
+                } else {
+                    &slots[ENTRIES_PER_BUCKET - 1]
+                }
+            }
+        };
+
+        let data = entry.pack();
+        target.key_xor_data.store(key ^ data, Ordering::Relaxed);
+        target.data.store(data, Ordering::Relaxed);
+    }
+
+    /// Permille of the table filled by the current search, estimated on the first entries.
+    pub fn hashfull(&self) -> u32 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample = 1000 / ENTRIES_PER_BUCKET;
+        let used: usize = self.buckets[..sample.min(self.buckets.len())]
+            .iter()
+            .map(|bucket| {
+                bucket
+                    .0
+                    .iter()
+                    .filter(|entry| {
+                        let data = entry.data.load(Ordering::Relaxed);
+                        data != 0 && TtEntry::unpack(data).age == age
+                    })
+                    .count()
+            })
+            .sum();
+        (used * 1000 / (sample * ENTRIES_PER_BUCKET)) as u32
+    }
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        TranspositionTable::new(TranspositionTable::DEFAULT_SIZE_MB)
+    }
+}
+
+/// Mate scores are stored as the distance to mate from the stored position rather than from the
+/// root, as the position may be reached at another ply.
+pub const fn score_to_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score + ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+pub const fn score_from_tt(score: i32, ply: usize) -> i32 {
+    if score >= MATE - MAX_PLY as i32 {
+        score - ply as i32
+    } else if score <= -MATE + MAX_PLY as i32 {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::r#move::{Move, PackedMove};
+    use crate::core::square_constants::*;
+    use crate::core::Piece::KNIGHT;
+    use crate::search::searcher::Searcher;
+    use crate::search::transposition_table::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    pub fn store_and_probe() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.size_mb(), 1);
+        let best_move = PackedMove::from(Move::new(KNIGHT, SQ_G1, SQ_F3));
+        assert_eq!(tt.probe(42), None);
+        tt.store(42, best_move, -150, 7, Bound::Upper);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!(entry.best_move, best_move);
+        assert_eq!(entry.score, -150);
+        assert_eq!(entry.depth, 7);
+        assert_eq!(entry.bound, Bound::Upper);
+        // same bucket, other position
+        let other = 42 + (1 << 40);
+        assert_eq!(tt.probe(other), None);
+
+        // a result without a move keeps the one found before
+        tt.store(42, PackedMove::NULL, 30, 8, Bound::Lower);
+        let entry = tt.probe(42).unwrap();
+        assert_eq!((entry.best_move, entry.score), (best_move, 30));
+        // a shallower bound of the same search doesn't replace a deeper result
+        tt.store(42, PackedMove::NULL, 10, 2, Bound::Upper);
+        assert_eq!(tt.probe(42).unwrap().score, 30);
+        tt.store(42, PackedMove::NULL, 20, 2, Bound::Exact);
+        assert_eq!(tt.probe(42).unwrap().score, 20);
+    }
+
+    #[test]
+    pub fn mate_scores() {
+        let score = mate_in(5);
+        assert_eq!(score_from_tt(score_to_tt(score, 3), 3), score);
+        // mate in 5 plies from the root found at ply 3 is mate in 2 plies from there
+        assert_eq!(score_to_tt(score, 3), mate_in(2));
+        assert_eq!(score_from_tt(score_to_tt(score, 3), 1), mate_in(3));
+        assert_eq!(score_to_tt(mated_in(4), 4), mated_in(0));
+        assert_eq!(score_to_tt(250, 4), 250);
+    }
+
+    #[test]
+    pub fn replacement() {
+        let mut tt = TranspositionTable::new(1);
+        let buckets = tt.buckets.len() as u64;
+        // positions of the same bucket
+        let key = |i: u64| 5 + i * buckets;
+        for i in 0..3 {
+            tt.store(key(i), PackedMove::NULL, 0, 10, Bound::Exact);
+        }
+        // the depth-preferred slots are full of deeper entries
+        tt.store(key(3), PackedMove::NULL, 0, 1, Bound::Exact);
+        tt.store(key(4), PackedMove::NULL, 0, 1, Bound::Exact);
+        assert!((0..3).all(|i| tt.probe(key(i)).is_some()));
+        assert_eq!(tt.probe(key(3)), None);
+        assert!(tt.probe(key(4)).is_some());
+        // entries of older searches lose their worth
+        for _ in 0..2 {
+            tt.new_search();
+        }
+        tt.store(key(5), PackedMove::NULL, 0, 1, Bound::Exact);
+        assert!(tt.probe(key(5)).is_some());
+        assert_eq!((0..3).filter(|&i| tt.probe(key(i)).is_some()).count(), 2);
+
+        tt.clear();
+        assert!((0..6).all(|i| tt.probe(key(i)).is_none()));
+        tt.resize(2);
+        assert_eq!(tt.size_mb(), 2);
+        assert_eq!(tt.buckets.len() as u64, 2 * buckets);
+    }
+
+    #[test]
+    pub fn hashfull() {
+        let tt = TranspositionTable::new(1);
+        assert_eq!(tt.hashfull(), 0);
+        // two entries in each of the sampled buckets
+        let buckets = tt.buckets.len() as u64;
+        for key in (0..250).flat_map(|i| [i, i + buckets]) {
+            tt.store(key, PackedMove::NULL, 0, 1, Bound::Exact);
+        }
+        assert_eq!(tt.hashfull(), 500);
+        tt.new_search();
+        assert_eq!(tt.hashfull(), 0);
+    }
+
+    #[test]
+    pub fn shared_between_threads() {
+        let tt = Arc::new(TranspositionTable::new(1));
+        let writers: Vec<_> = (0..4u64)
+            .map(|thread| {
+                let tt = tt.clone();
+                thread::spawn(move || {
+                    for i in 0..100_000u64 {
+                        let key = i.wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ thread;
+                        tt.store(key, PackedMove::NULL, key as i16 as i32, 1, Bound::Exact);
+                        if let Some(entry) = tt.probe(key) {
+                            assert_eq!(entry.score, key as i16 as i32);
+                        }
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+    }
+
+    #[test]
+    pub fn search_fills_table() {
+        let board = Board::from_initial_position();
+        let tt = Arc::new(TranspositionTable::new(1));
+        let mut searcher = Searcher::with_transposition_table(MaterialEvaluator, tt.clone());
+        // the selective search visits too few nodes to fill a thousandth of the table
+        searcher.set_parameters(SearchParameters::none());
+        let first = searcher.search(&board, &SearchLimits::depth(5));
+        assert!(tt.hashfull() > 0);
+        let entry = tt.probe(crate::core::zobrist_hash::hash(&board)).unwrap();
+        assert_eq!(entry.bound, Bound::Exact);
+        assert_eq!(entry.best_move.unpack(&board), first.best_move);
+
+        // the table of the previous search cuts the same search short
+        let second = searcher.search(&board, &SearchLimits::depth(5));
+        assert_eq!(second.score, first.score);
+        assert!(second.nodes < first.nodes);
+    }
+}
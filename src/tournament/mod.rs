@@ -0,0 +1,537 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::hash;
+use crate::core::Color;
+use crate::core::Color::*;
+use crate::tournament::openings::Opening;
+use crate::tournament::pgn::PgnGame;
+use crate::tournament::stats::{MatchScore, Sprt, SprtStatus};
+use crate::uci::client::{EngineScore, Info, UciClient};
+use crate::uci::GoCommand;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+
+pub mod openings;
+pub mod pgn;
+pub mod stats;
+
+/// Centipawn score standing for a mate reported by an engine.
+const MATE_CENTIPAWNS: i32 = 100000;
+
+/// A time control: the time of each side for the whole game, and the time added after each
+/// move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeControl {
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl TimeControl {
+    /// Parses a time control in seconds, e.g. `60`, `10+0.1` or `0.5+0.05`.
+    pub fn parse(text: &str) -> Option<TimeControl> {
+        let (base, increment) = text.split_once('+').unwrap_or((text, "0"));
+        let seconds = |text: &str| {
+            let seconds = text.parse::<f64>().ok()?;
+            (seconds.is_finite() && seconds >= 0.0).then(|| Duration::from_secs_f64(seconds))
+        };
+        Some(TimeControl {
+            base: seconds(base)?,
+            increment: seconds(increment)?,
+        })
+    }
+}
+
+impl Display for TimeControl {
+    /// Writes the time control as in the `TimeControl` PGN tag, e.g. `10+0.1`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}+{}",
+            self.base.as_secs_f64(),
+            self.increment.as_secs_f64()
+        )
+    }
+}
+
+/// Thresholds to end games that are clearly decided before the rules do. Scores are in
+/// centipawns, moves count the moves of each engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Adjudication {
+    /// An engine resigns when its own score stays at or below minus this for `resign_moves`
+    /// moves in a row.
+    pub resign_score: Option<i32>,
+    pub resign_moves: u32,
+    /// The game is drawn when the scores of both engines stay within this of 0 for
+    /// `draw_moves` moves each, once `draw_move_number` moves have been played.
+    pub draw_score: Option<i32>,
+    pub draw_moves: u32,
+    pub draw_move_number: u32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    /// The result of the game lost by `color`.
+    pub fn loss(color: Color) -> GameResult {
+        match color {
+            WHITE => GameResult::BlackWins,
+            BLACK => GameResult::WhiteWins,
+        }
+    }
+
+    /// The points scored by `color`: 1, 0.5 or 0.
+    pub fn points(self, color: Color) -> f64 {
+        match (self, color) {
+            (GameResult::Draw, _) => 0.5,
+            (GameResult::WhiteWins, WHITE) | (GameResult::BlackWins, BLACK) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+        }
+    }
+}
+
+/// Why a game ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    Repetition,
+    FiftyMoves,
+    InsufficientMaterial,
+    Resignation,
+    DrawAdjudication,
+    TimeForfeit,
+    /// The engine sent a move that isn't legal, or no move in a position with legal moves.
+    IllegalMove,
+    /// The engine crashed or stopped answering.
+    Disconnection,
+}
+
+impl Termination {
+    /// The value of the `Termination` PGN tag.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Termination::Resignation | Termination::DrawAdjudication => "adjudication",
+            Termination::TimeForfeit => "time forfeit",
+            Termination::IllegalMove => "rules infraction",
+            Termination::Disconnection => "abandoned",
+            _ => "normal",
+        }
+    }
+}
+
+impl Display for Termination {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Repetition => "threefold repetition",
+            Termination::FiftyMoves => "fifty move rule",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Resignation => "resignation",
+            Termination::DrawAdjudication => "draw adjudication",
+            Termination::TimeForfeit => "loss on time",
+            Termination::IllegalMove => "illegal move",
+            Termination::Disconnection => "engine disconnected",
+        })
+    }
+}
+
+/// The result of the game if the rules end it in `board`: mate, stalemate, threefold
+/// repetition, the fifty move rule or insufficient material. `history` holds the hashes of
+/// the positions played before.
+pub fn rules_result(board: &Board, history: &[u64]) -> Option<(GameResult, Termination)> {
+    let mut generator = MoveGenerator::new(board);
+    generator.generate();
+    if generator.moves.is_empty() {
+        return Some(if board.checkers().empty() {
+            (GameResult::Draw, Termination::Stalemate)
+        } else {
+            (GameResult::loss(board.side_to_play), Termination::Checkmate)
+        });
+    }
+    let current = hash(board);
+    if history.iter().filter(|&&h| h == current).count() >= 2 {
+        return Some((GameResult::Draw, Termination::Repetition));
+    }
+    if board.half_move_counter >= 100 {
+        return Some((GameResult::Draw, Termination::FiftyMoves));
+    }
+    if board.insufficient_material() {
+        return Some((GameResult::Draw, Termination::InsufficientMaterial));
+    }
+    None
+}
+
+/// A game played by two engines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Game {
+    pub white: String,
+    pub black: String,
+    pub opening: Opening,
+    /// The moves played by the engines after the opening.
+    pub moves: Vec<Move>,
+    pub result: GameResult,
+    pub termination: Termination,
+    pub time_control: TimeControl,
+}
+
+impl Game {
+    /// The game as PGN, with the opening moves and the moves of the engines.
+    pub fn to_pgn(&self, event: &str, round: u32) -> String {
+        let mut moves = self.opening.moves.clone();
+        moves.extend_from_slice(&self.moves);
+        let tag = |name: &str, value: String| (name.to_string(), value);
+        let tags = vec![
+            tag("Event", event.to_string()),
+            tag("Site", String::from("?")),
+            tag("Round", round.to_string()),
+            tag("White", self.white.clone()),
+            tag("Black", self.black.clone()),
+            tag("Result", self.result.as_str().to_string()),
+            tag("TimeControl", self.time_control.to_string()),
+            tag("Termination", self.termination.tag().to_string()),
+            tag("PlyCount", moves.len().to_string()),
+        ];
+        PgnGame {
+            tags,
+            board: self.opening.board,
+            moves,
+            result: self.result.as_str().to_string(),
+        }
+        .to_pgn()
+    }
+}
+
+/// Plays a game between two engines from `opening`, with clocks following `time_control`. An
+/// engine still searching when its clock runs out is stopped and loses on time.
+pub fn play_game(
+    white: &mut UciClient,
+    black: &mut UciClient,
+    opening: &Opening,
+    time_control: TimeControl,
+    adjudication: &Adjudication,
+) -> Game {
+    let mut game = Game {
+        white: white.name().unwrap_or("white").to_string(),
+        black: black.name().unwrap_or("black").to_string(),
+        opening: opening.clone(),
+        moves: Vec::new(),
+        result: GameResult::Draw,
+        termination: Termination::Disconnection,
+        time_control,
+    };
+    let mut engines = [white, black];
+    for (color, engine) in [WHITE, BLACK].into_iter().zip(engines.iter_mut()) {
+        if engine.new_game().is_err() {
+            game.result = GameResult::loss(color);
+            return game;
+        }
+    }
+
+    let mut board = opening.board;
+    let mut history = Vec::new();
+    for &m in opening.moves.iter() {
+        history.push(hash(&board));
+        board.make_move(m);
+    }
+    let mut clocks = [time_control.base; 2];
+    let mut resign_counts = [0; 2];
+    let mut draw_counts = [0; 2];
+    let (result, termination) = loop {
+        if let Some(end) = rules_result(&board, &history) {
+            break end;
+        }
+        let us = board.side_to_play;
+        let engine = &mut engines[us];
+        let mut all_moves = opening.moves.clone();
+        all_moves.extend_from_slice(&game.moves);
+        let go = GoCommand {
+            wtime: Some(clocks[WHITE]),
+            btime: Some(clocks[BLACK]),
+            winc: Some(time_control.increment),
+            binc: Some(time_control.increment),
+            ..GoCommand::default()
+        };
+        let start = Instant::now();
+        let mut score = None;
+        // an engine that doesn't answer within its clock would stall the match
+        let best_move = engine
+            .set_position(&opening.board, &all_moves)
+            .and_then(|_| engine.start_search(&go))
+            .and_then(|_| {
+                engine.wait_for_best_move(Some(clocks[us]), |info| keep_score(&mut score, info))
+            });
+        let elapsed = start.elapsed();
+        let best_move = match best_move {
+            Ok(best_move) => best_move.best_move,
+            Err(error) if error.kind() == ErrorKind::TimedOut => {
+                let _ = engine.stop();
+                break (GameResult::loss(us), Termination::TimeForfeit);
+            }
+            Err(_) => break (GameResult::loss(us), Termination::Disconnection),
+        };
+        let Some(best_move) = best_move else {
+            break (GameResult::loss(us), Termination::IllegalMove);
+        };
+        if elapsed > clocks[us] {
+            break (GameResult::loss(us), Termination::TimeForfeit);
+        }
+        clocks[us] = clocks[us] - elapsed + time_control.increment;
+        history.push(hash(&board));
+        board.make_move(best_move);
+        game.moves.push(best_move);
+
+        let score = score.map(|(_, score)| match score {
+            EngineScore::Centipawns(centipawns) => centipawns,
+            EngineScore::Mate(moves) if moves > 0 => MATE_CENTIPAWNS,
+            EngineScore::Mate(_) => -MATE_CENTIPAWNS,
+        });
+        if let Some(end) = adjudicate(
+            adjudication,
+            us,
+            score,
+            game.moves.len(),
+            &mut resign_counts,
+            &mut draw_counts,
+        ) {
+            break end;
+        }
+    };
+    game.result = result;
+    game.termination = termination;
+    game
+}
+
+/// Keeps the score of `info` and its depth if it is the exact score of the best line, as deep
+/// as the one kept so far: bounds and the other MultiPV lines don't score the best move.
+fn keep_score(score: &mut Option<(u32, EngineScore)>, info: &Info) {
+    let Some(new_score) = info.score else {
+        return;
+    };
+    if info.lowerbound || info.upperbound || info.multipv.is_some_and(|index| index != 1) {
+        return;
+    }
+    let depth = info.depth.unwrap_or(0);
+    if score.is_none_or(|(kept_depth, _)| depth >= kept_depth) {
+        *score = Some((depth, new_score));
+    }
+}
+
+/// Updates the counts of moves beyond the resign and draw thresholds after a move of
+/// `color` with `score`, and returns the result if they are reached.
+fn adjudicate(
+    adjudication: &Adjudication,
+    color: Color,
+    score: Option<i32>,
+    moves_played: usize,
+    resign_counts: &mut [u32; 2],
+    draw_counts: &mut [u32; 2],
+) -> Option<(GameResult, Termination)> {
+    let us = color;
+    if let Some(resign_score) = adjudication.resign_score {
+        match score {
+            Some(score) if score <= -resign_score => resign_counts[us] += 1,
+            _ => resign_counts[us] = 0,
+        }
+        if resign_counts[us] >= adjudication.resign_moves.max(1) {
+            return Some((GameResult::loss(color), Termination::Resignation));
+        }
+    }
+    if let Some(draw_score) = adjudication.draw_score {
+        match score {
+            Some(score) if score.abs() <= draw_score => draw_counts[us] += 1,
+            _ => draw_counts[us] = 0,
+        }
+        let draw_moves = adjudication.draw_moves.max(1);
+        if moves_played / 2 >= adjudication.draw_move_number as usize
+            && draw_counts.iter().all(|&count| count >= draw_moves)
+        {
+            return Some((GameResult::Draw, Termination::DrawAdjudication));
+        }
+    }
+    None
+}
+
+/// The settings of a match between two engines.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MatchConfig {
+    /// Every opening is played twice, with the colors reversed. The standard starting
+    /// position is used when there are none.
+    pub openings: Vec<Opening>,
+    pub games: u32,
+    pub time_control: TimeControl,
+    pub adjudication: Adjudication,
+    /// Stops the match once the test accepts a hypothesis.
+    pub sprt: Option<Sprt>,
+}
+
+/// Plays a match between two engines, calling `on_game` with every game and the score so
+/// far, and returns the score of the first engine.
+pub fn run_match(
+    engines: &mut [UciClient; 2],
+    config: &MatchConfig,
+    mut on_game: impl FnMut(&Game, &MatchScore),
+) -> MatchScore {
+    let default_openings = [Opening::new(Board::from_initial_position())];
+    let openings = if config.openings.is_empty() {
+        &default_openings[..]
+    } else {
+        &config.openings[..]
+    };
+    let mut score = MatchScore::default();
+    for i in 0..config.games {
+        let opening = &openings[(i as usize / 2) % openings.len()];
+        let [first, second] = engines;
+        let first_color = if i % 2 == 0 { WHITE } else { BLACK };
+        let (white, black) = match first_color {
+            WHITE => (first, second),
+            BLACK => (second, first),
+        };
+        let game = play_game(
+            white,
+            black,
+            opening,
+            config.time_control,
+            &config.adjudication,
+        );
+        score.add(game.result.points(first_color));
+        on_game(&game, &score);
+        if config
+            .sprt
+            .is_some_and(|sprt| sprt.status(&score) != SprtStatus::Continue)
+        {
+            break;
+        }
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::tournament::*;
+
+    #[test]
+    pub fn time_controls() {
+        let tc = TimeControl::parse("10+0.1").unwrap();
+        assert_eq!(tc.base, Duration::from_secs(10));
+        assert_eq!(tc.increment, Duration::from_millis(100));
+        assert_eq!(tc.to_string(), "10+0.1");
+        assert_eq!(TimeControl::parse("60").unwrap().increment, Duration::ZERO);
+        assert_eq!(TimeControl::parse("1+x"), None);
+        assert_eq!(TimeControl::parse("-1+1"), None);
+    }
+
+    #[test]
+    pub fn rules() {
+        let result = |fen| rules_result(&board_from_fen(fen).unwrap(), &[]);
+        assert_eq!(
+            result("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1"),
+            Some((GameResult::WhiteWins, Termination::Checkmate))
+        );
+        assert_eq!(
+            result("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"),
+            Some((GameResult::Draw, Termination::Stalemate))
+        );
+        assert_eq!(
+            result("4k3/8/8/8/8/8/8/3QK3 b - - 100 80"),
+            Some((GameResult::Draw, Termination::FiftyMoves))
+        );
+        assert_eq!(
+            result("4k3/8/8/8/8/8/8/3NK3 b - - 0 1"),
+            Some((GameResult::Draw, Termination::InsufficientMaterial))
+        );
+        assert_eq!(result("4k3/8/8/8/8/8/8/3QK3 b - - 0 1"), None);
+
+        let mut board = Board::from_initial_position();
+        let mut history = vec![];
+        for _ in 0..2 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                history.push(hash(&board));
+                board.make_move(Move::from_uci(uci, &board).unwrap());
+            }
+        }
+        assert_eq!(
+            rules_result(&board, &history),
+            Some((GameResult::Draw, Termination::Repetition))
+        );
+        assert_eq!(GameResult::WhiteWins.points(BLACK), 0.0);
+        assert_eq!(GameResult::loss(WHITE).as_str(), "0-1");
+    }
+
+    #[test]
+    pub fn scores_kept_for_adjudication() {
+        let board = Board::from_initial_position();
+        let mut score = None;
+        for params in [
+            "depth 1 score cp 20",
+            "depth 2 score cp 30",
+            "depth 3 score cp 90 lowerbound",
+            "depth 3 multipv 2 score cp -50",
+            "depth 3 multipv 1 score cp 40",
+            "depth 2 score cp -10",
+            "nodes 1000",
+        ] {
+            keep_score(&mut score, &Info::parse(params, &board));
+        }
+        assert_eq!(score, Some((3, EngineScore::Centipawns(40))));
+    }
+
+    #[test]
+    pub fn adjudication() {
+        let adjudication = Adjudication {
+            resign_score: Some(500),
+            resign_moves: 2,
+            draw_score: Some(10),
+            draw_moves: 2,
+            draw_move_number: 3,
+        };
+        let mut resign = [0; 2];
+        let mut draw = [0; 2];
+        let mut play = |color, score, moves| {
+            adjudicate(&adjudication, color, score, moves, &mut resign, &mut draw)
+        };
+        assert_eq!(play(WHITE, Some(-600), 1), None);
+        assert_eq!(play(BLACK, Some(600), 2), None);
+        // the streak is broken by a move without score
+        assert_eq!(play(WHITE, None, 3), None);
+        assert_eq!(play(BLACK, Some(600), 4), None);
+        assert_eq!(play(WHITE, Some(-600), 5), None);
+        assert_eq!(play(BLACK, Some(0), 6), None);
+        assert_eq!(
+            play(WHITE, Some(-MATE_CENTIPAWNS), 7),
+            Some((GameResult::BlackWins, Termination::Resignation))
+        );
+
+        let mut resign = [0; 2];
+        let mut draw = [0; 2];
+        let mut play = |color, score, moves| {
+            adjudicate(&adjudication, color, score, moves, &mut resign, &mut draw)
+        };
+        for moves in 1..=5 {
+            let color = if moves % 2 == 1 { WHITE } else { BLACK };
+            // drawish from the start, but not before move 3
+            assert_eq!(play(color, Some(5), moves), None);
+        }
+        assert_eq!(
+            play(BLACK, Some(-5), 6),
+            Some((GameResult::Draw, Termination::DrawAdjudication))
+        );
+    }
+}
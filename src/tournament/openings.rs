@@ -0,0 +1,106 @@
+use crate::core::board::Board;
+use crate::core::epd::epd_from_str;
+use crate::core::r#move::Move;
+use crate::tournament::pgn::parse_pgn;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+/// The start of a game: a position and the moves played from it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Opening {
+    pub board: Board,
+    pub moves: Vec<Move>,
+}
+
+impl Opening {
+    pub fn new(board: Board) -> Opening {
+        Opening {
+            board,
+            moves: Vec::new(),
+        }
+    }
+
+    /// The position after the moves of the opening.
+    pub fn position(&self) -> Board {
+        let mut board = self.board;
+        for &m in self.moves.iter() {
+            board.make_move(m);
+        }
+        board
+    }
+}
+
+/// Reads one opening per EPD line, skipping empty lines. Returns `None` if a line isn't
+/// valid.
+pub fn openings_from_epd(text: &str) -> Option<Vec<Opening>> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| epd_from_str(line).map(|epd| Opening::new(epd.board)))
+        .collect()
+}
+
+/// Reads one opening per game of a PGN file, made of the moves of the game.
+pub fn openings_from_pgn(text: &str) -> Option<Vec<Opening>> {
+    let games = parse_pgn(text)?;
+    Some(
+        games
+            .into_iter()
+            .map(|game| Opening {
+                board: game.board,
+                moves: game.moves,
+            })
+            .collect(),
+    )
+}
+
+/// Reads the openings of a `.pgn` file, or of an EPD file for any other extension.
+pub fn load_openings(path: &Path) -> Result<Vec<Opening>, Error> {
+    let text = std::fs::read_to_string(path)?;
+    let is_pgn = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pgn"));
+    let openings = if is_pgn {
+        openings_from_pgn(&text)
+    } else {
+        openings_from_epd(&text)
+    };
+    openings.ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid openings in {}", path.display()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::board_from_fen;
+    use crate::core::san::line_to_san;
+    use crate::tournament::openings::*;
+
+    #[test]
+    pub fn read_openings() {
+        let openings = openings_from_epd(
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"e4\";\n\n\
+             rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq -\n",
+        )
+        .unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(
+            openings[1].position(),
+            board_from_fen("rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1").unwrap()
+        );
+        assert_eq!(openings_from_epd("not an epd"), None);
+
+        let openings = openings_from_pgn("1. e4 c5 2. Nf3 *\n1. d4 Nf6 *").unwrap();
+        assert_eq!(openings.len(), 2);
+        assert_eq!(
+            line_to_san(&openings[0].board, &openings[0].moves),
+            "e4 c5 Nf3"
+        );
+        assert_eq!(
+            openings[1].position().side_to_play,
+            crate::core::Color::WHITE
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::core::board::Board;
+use crate::core::fen::{board_from_fen, to_string};
+use crate::core::r#move::Move;
+use crate::core::Color::*;
+
+/// Longest line of movetext written.
+const LINE_LENGTH: usize = 80;
+
+/// A game in Portable Game Notation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnGame {
+    /// Tag pairs in order, e.g. `("White", "chess-core")`.
+    pub tags: Vec<(String, String)>,
+    /// The starting position, from the `FEN` tag if any.
+    pub board: Board,
+    pub moves: Vec<Move>,
+    /// `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Writes the game, adding the `FEN` and `SetUp` tags for a non standard starting
+    /// position. The movetext is wrapped at 80 columns.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+        for (name, value) in self.tags.iter() {
+            pgn += &format!(
+                "[{} \"{}\"]\n",
+                name,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            );
+        }
+        if self.board != Board::from_initial_position() && self.tag("FEN").is_none() {
+            pgn += "[SetUp \"1\"]\n";
+            pgn += &format!("[FEN \"{}\"]\n", to_string(&self.board, 1));
+        }
+        pgn += "\n";
+
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 / 2 + 1);
+        let mut board = self.board;
+        for (i, &m) in self.moves.iter().enumerate() {
+            let number = i / 2 + 1;
+            if board.side_to_play == WHITE {
+                tokens.push(format!("{}.", number));
+            } else if i == 0 {
+                tokens.push(format!("{}...", number));
+            }
+            tokens.push(m.to_san(&board));
+            board.make_move(m);
+        }
+        tokens.push(self.result.clone());
+
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > LINE_LENGTH {
+                pgn += &line;
+                pgn += "\n";
+                line.clear();
+            }
+            if !line.is_empty() {
+                line += " ";
+            }
+            line += &token;
+        }
+        pgn + &line + "\n\n"
+    }
+}
+
+/// Parses the games of a PGN file. Comments, variations, annotations and move numbers are
+/// skipped. Returns `None` if a move isn't legal or a `FEN` tag is invalid.
+pub fn parse_pgn(text: &str) -> Option<Vec<PgnGame>> {
+    let mut games = Vec::new();
+    let mut game: Option<PgnGame> = None;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                if game.as_ref().is_some_and(|game| !game.moves.is_empty()) {
+                    games.push(game.take().unwrap());
+                }
+                let mut tag = String::new();
+                let mut in_string = false;
+                loop {
+                    match chars.next()? {
+                        '\\' if in_string => tag.push(chars.next()?),
+                        '"' => in_string = !in_string,
+                        ']' if !in_string => break,
+                        c => tag.push(c),
+                    }
+                }
+                let (name, value) = tag.split_once(' ').unwrap_or((&tag, ""));
+                let game = game.get_or_insert_with(new_game);
+                if name == "FEN" {
+                    game.board = board_from_fen(value)?;
+                }
+                game.tags.push((name.to_string(), value.to_string()));
+            }
+            '{' => while chars.next()? != '}' {},
+            ';' => while chars.next().is_some_and(|c| c != '\n') {},
+            '(' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next()? {
+                        '(' => depth += 1,
+                        ')' => depth -= 1,
+                        _ => {}
+                    }
+                }
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut token = String::from(c);
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "{}()[];".contains(c) {
+                        break;
+                    }
+                    token.push(c);
+                    chars.next();
+                }
+                let current = game.get_or_insert_with(new_game);
+                match token.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" | "*" => {
+                        current.result = token;
+                        games.push(game.take().unwrap());
+                    }
+                    _ if token.starts_with('$') => {}
+                    _ => {
+                        // move numbers may be attached to the move, e.g. `12.Nf3`
+                        let san =
+                            token.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                        if !san.is_empty() {
+                            let mut board = current.board;
+                            for &m in current.moves.iter() {
+                                board.make_move(m);
+                            }
+                            current.moves.push(Move::from_san(san, &board)?);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    games.extend(game);
+    Some(games)
+}
+
+fn new_game() -> PgnGame {
+    PgnGame {
+        tags: Vec::new(),
+        board: Board::from_initial_position(),
+        moves: Vec::new(),
+        result: String::from("*"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::san::line_to_san;
+    use crate::tournament::pgn::*;
+
+    #[test]
+    pub fn parse_games() {
+        let text = r#"[Event "Test"]
+[White "A \"quoted\" name"]
+[Result "1-0"]
+
+1. e4 e5 {a comment} 2. Nf3 (2. f4 exf4) Nc6 $1 3.Bb5 ; the Ruy Lopez
+a6 1-0
+
+[Event "Test"]
+[SetUp "1"]
+[FEN "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1"]
+
+1. Ra8# 1-0
+"#;
+        let games = parse_pgn(text).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].tag("White"), Some("A \"quoted\" name"));
+        assert_eq!(games[0].result, "1-0");
+        assert_eq!(
+            line_to_san(&games[0].board, &games[0].moves),
+            "e4 e5 Nf3 Nc6 Bb5 a6"
+        );
+        assert_eq!(
+            games[1].board,
+            board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap()
+        );
+        assert_eq!(games[1].moves.len(), 1);
+
+        assert_eq!(parse_pgn("1. e4 e4"), None);
+        // a game without tags or result
+        assert_eq!(parse_pgn("1. d4 d5").unwrap()[0].moves.len(), 2);
+    }
+
+    #[test]
+    pub fn write_games() {
+        let mut game = parse_pgn("1. e4 e5 2. Nf3 Nc6 1/2-1/2").unwrap().remove(0);
+        game.tags
+            .push((String::from("White"), String::from("A \"B\"")));
+        assert_eq!(
+            game.to_pgn(),
+            "[White \"A \\\"B\\\"\"]\n\n1. e4 e5 2. Nf3 Nc6 1/2-1/2\n\n"
+        );
+        assert_eq!(parse_pgn(&game.to_pgn()).unwrap()[0], game);
+
+        let board = board_from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let game = PgnGame {
+            tags: vec![],
+            board,
+            moves: vec![],
+            result: String::from("*"),
+        };
+        assert_eq!(
+            game.to_pgn(),
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n\n*\n\n"
+        );
+
+        // long games are wrapped
+        let mut board = Board::from_initial_position();
+        let mut moves = vec![];
+        for _ in 0..10 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                let m = crate::core::r#move::Move::from_uci(uci, &board).unwrap();
+                board.make_move(m);
+                moves.push(m);
+            }
+        }
+        let game = PgnGame {
+            tags: vec![],
+            board: Board::from_initial_position(),
+            moves,
+            result: String::from("*"),
+        };
+        let pgn = game.to_pgn();
+        assert!(pgn.lines().all(|line| line.len() <= 80));
+        assert_eq!(parse_pgn(&pgn).unwrap()[0].moves, game.moves);
+    }
+}
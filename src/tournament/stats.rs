@@ -0,0 +1,195 @@
+/// Quantile of the normal distribution for a 95% confidence interval.
+const Z_95: f64 = 1.959964;
+
+/// Converts an expected score between 0 and 1 to an Elo difference.
+pub fn elo_from_score(score: f64) -> f64 {
+    400.0 * (score / (1.0 - score)).log10()
+}
+
+/// Converts an Elo difference to the expected score of the stronger side.
+pub fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+/// The results of a match from the point of view of the first engine.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MatchScore {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl MatchScore {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Adds a game where the first engine scored `points`: 1, 0.5 or 0.
+    pub fn add(&mut self, points: f64) {
+        if points > 0.5 {
+            self.wins += 1;
+        } else if points < 0.5 {
+            self.losses += 1;
+        } else {
+            self.draws += 1;
+        }
+    }
+
+    /// The fraction of the points scored by the first engine.
+    pub fn score(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    /// The Elo difference and the half width of its 95% confidence interval, infinite when
+    /// one engine scored every point, or `None` before the first game.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+        let games = self.games() as f64;
+        let score = self.score();
+        let variance = (self.wins as f64 * (1.0 - score).powi(2)
+            + self.draws as f64 * (0.5 - score).powi(2)
+            + self.losses as f64 * score.powi(2))
+            / games;
+        let margin = Z_95 * (variance / games).sqrt();
+        let low = elo_from_score((score - margin).max(0.0));
+        let high = elo_from_score((score + margin).min(1.0));
+        Some((elo_from_score(score), (high - low) / 2.0))
+    }
+
+    /// The log-likelihood ratio of `elo1` against `elo0`, with the normal approximation of the
+    /// trinomial model. It is 0 until there is at least a win, a draw and a loss.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        if self.wins == 0 || self.draws == 0 || self.losses == 0 {
+            return 0.0;
+        }
+        let games = self.games() as f64;
+        let (wins, draws) = (self.wins as f64 / games, self.draws as f64 / games);
+        let score = wins + draws / 2.0;
+        let variance = (wins + draws / 4.0 - score * score) / games;
+        let (score0, score1) = (score_from_elo(elo0), score_from_elo(elo1));
+        (score1 - score0) * (2.0 * score - score0 - score1) / (2.0 * variance)
+    }
+}
+
+/// The outcome of a sequential probability ratio test.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SprtStatus {
+    Continue,
+    /// The first engine isn't `elo1` stronger.
+    AcceptH0,
+    /// The first engine isn't `elo0` stronger or weaker.
+    AcceptH1,
+}
+
+/// A sequential probability ratio test of the hypotheses that the first engine is `elo0` or
+/// `elo1` stronger, with error rates `alpha` and `beta`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sprt {
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Sprt {
+    pub fn new(elo0: f64, elo1: f64) -> Sprt {
+        Sprt {
+            elo0,
+            elo1,
+            alpha: 0.05,
+            beta: 0.05,
+        }
+    }
+
+    /// The log-likelihood ratios accepting H0 and H1.
+    pub fn bounds(&self) -> (f64, f64) {
+        (
+            (self.beta / (1.0 - self.alpha)).ln(),
+            ((1.0 - self.beta) / self.alpha).ln(),
+        )
+    }
+
+    pub fn llr(&self, score: &MatchScore) -> f64 {
+        score.llr(self.elo0, self.elo1)
+    }
+
+    pub fn status(&self, score: &MatchScore) -> SprtStatus {
+        let llr = self.llr(score);
+        let (lower, upper) = self.bounds();
+        if llr <= lower {
+            SprtStatus::AcceptH0
+        } else if llr >= upper {
+            SprtStatus::AcceptH1
+        } else {
+            SprtStatus::Continue
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tournament::stats::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 0.01,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    pub fn elo() {
+        assert_close(elo_from_score(0.5), 0.0);
+        assert_close(score_from_elo(elo_from_score(0.7)), 0.7);
+        let score = MatchScore {
+            wins: 60,
+            draws: 20,
+            losses: 20,
+        };
+        let (elo, error) = score.elo().unwrap();
+        assert_close(elo, 147.19);
+        assert!(error > 50.0 && error < 100.0, "{}", error);
+        assert_eq!(MatchScore::default().elo(), None);
+
+        let mut score = MatchScore::default();
+        score.add(1.0);
+        assert_eq!(score.elo().unwrap().0, f64::INFINITY);
+        score.add(0.5);
+        score.add(0.0);
+        assert_eq!((score.wins, score.draws, score.losses), (1, 1, 1));
+        assert_close(score.elo().unwrap().0, 0.0);
+    }
+
+    #[test]
+    pub fn sprt() {
+        let sprt = Sprt::new(0.0, 5.0);
+        let (lower, upper) = sprt.bounds();
+        assert_close(lower, -2.94);
+        assert_close(upper, 2.94);
+
+        let score = MatchScore {
+            wins: 100,
+            draws: 100,
+            losses: 80,
+        };
+        assert_close(sprt.llr(&score), 0.406);
+        assert_eq!(sprt.status(&score), SprtStatus::Continue);
+        let score = MatchScore {
+            wins: 3000,
+            draws: 3000,
+            losses: 2400,
+        };
+        assert_eq!(sprt.status(&score), SprtStatus::AcceptH1);
+        let score = MatchScore {
+            wins: 2400,
+            draws: 3000,
+            losses: 3000,
+        };
+        assert_eq!(sprt.status(&score), SprtStatus::AcceptH0);
+        assert_eq!(sprt.llr(&MatchScore::default()), 0.0);
+    }
+}
@@ -0,0 +1,475 @@
+use crate::core::board::Board;
+use crate::core::fen::to_string;
+use crate::core::r#move::Move;
+use crate::uci::GoCommand;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// Time an engine has to answer `uci` and `isready`.
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A score reported by an engine, from the point of view of the side to play.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EngineScore {
+    Centipawns(i32),
+    /// Moves to mate, negative when getting mated.
+    Mate(i32),
+}
+
+/// An `info` line. Fields the engine didn't send are `None` or empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    pub depth: Option<u32>,
+    pub seldepth: Option<u32>,
+    pub multipv: Option<u32>,
+    pub score: Option<EngineScore>,
+    /// The score is only a lower bound.
+    pub lowerbound: bool,
+    /// The score is only an upper bound.
+    pub upperbound: bool,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub time: Option<Duration>,
+    pub hashfull: Option<u32>,
+    pub currmove: Option<Move>,
+    pub pv: Vec<Move>,
+    pub string: Option<String>,
+}
+
+impl Info {
+    /// Parses the parameters following `info` for a search of `board`. Unknown parameters
+    /// and malformed values are skipped, the pv stops at the first move that isn't legal.
+    pub fn parse(params: &str, board: &Board) -> Info {
+        let mut info = Info::default();
+        let mut tokens = params.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            match token {
+                "depth" => info.depth = number(&mut tokens).map(|depth| depth as u32),
+                "seldepth" => info.seldepth = number(&mut tokens).map(|depth| depth as u32),
+                "multipv" => info.multipv = number(&mut tokens).map(|index| index as u32),
+                "nodes" => info.nodes = number(&mut tokens).map(|nodes| nodes as u64),
+                "nps" => info.nps = number(&mut tokens).map(|nps| nps as u64),
+                "time" => {
+                    info.time =
+                        number(&mut tokens).map(|millis| Duration::from_millis(millis as u64))
+                }
+                "hashfull" => info.hashfull = number(&mut tokens).map(|permille| permille as u32),
+                "score" => {
+                    info.score = match tokens.next() {
+                        Some("cp") => {
+                            number(&mut tokens).map(|cp| EngineScore::Centipawns(cp as i32))
+                        }
+                        Some("mate") => {
+                            number(&mut tokens).map(|moves| EngineScore::Mate(moves as i32))
+                        }
+                        _ => None,
+                    }
+                }
+                "lowerbound" => info.lowerbound = true,
+                "upperbound" => info.upperbound = true,
+                "currmove" => {
+                    info.currmove = tokens.next().and_then(|uci| Move::from_uci(uci, board))
+                }
+                "pv" => {
+                    let mut board = *board;
+                    while let Some(m) = tokens.peek().and_then(|uci| Move::from_uci(uci, &board)) {
+                        tokens.next();
+                        info.pv.push(m);
+                        board.make_move(m);
+                    }
+                }
+                // the rest of the line is the text
+                "string" => info.string = Some(tokens.by_ref().collect::<Vec<_>>().join(" ")),
+                _ => {}
+            }
+        }
+        info
+    }
+}
+
+fn number<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Option<i64> {
+    tokens.next().and_then(|value| value.parse().ok())
+}
+
+/// A `bestmove` line. The best move is `None` when the engine has no legal move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BestMove {
+    pub best_move: Option<Move>,
+    pub ponder: Option<Move>,
+}
+
+impl BestMove {
+    /// Parses the parameters following `bestmove` for a search of `board`, or returns `None`
+    /// if the best move isn't legal.
+    pub fn parse(params: &str, board: &Board) -> Option<BestMove> {
+        let mut tokens = params.split_whitespace();
+        let best_move = match tokens.next()? {
+            "0000" | "(none)" => None,
+            uci => Some(Move::from_uci(uci, board)?),
+        };
+        let ponder = match (best_move, tokens.next(), tokens.next()) {
+            (Some(best_move), Some("ponder"), Some(uci)) => {
+                let mut next = *board;
+                next.make_move(best_move);
+                Move::from_uci(uci, &next)
+            }
+            _ => None,
+        };
+        Some(BestMove { best_move, ponder })
+    }
+}
+
+/// An option declared by an engine during the handshake.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineOption {
+    pub name: String,
+    /// `check`, `spin`, `combo`, `button` or `string`.
+    pub option_type: String,
+    pub default: Option<String>,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    /// The values of a `combo` option.
+    pub vars: Vec<String>,
+}
+
+impl EngineOption {
+    /// Parses the parameters following `option`, e.g. `name Hash type spin default 16 min 1
+    /// max 1024`. Names and values may contain spaces.
+    pub fn parse(params: &str) -> Option<EngineOption> {
+        let mut option = EngineOption::default();
+        let mut fields: Vec<(&str, Vec<&str>)> = Vec::new();
+        for token in params.split_whitespace() {
+            match token {
+                "name" | "type" | "default" | "min" | "max" | "var" => {
+                    fields.push((token, Vec::new()))
+                }
+                _ => fields.last_mut()?.1.push(token),
+            }
+        }
+        for (keyword, words) in fields {
+            let value = words.join(" ");
+            match keyword {
+                "name" => option.name = value,
+                "type" => option.option_type = value,
+                "default" => option.default = Some(value),
+                "min" => option.min = value.parse().ok(),
+                "max" => option.max = value.parse().ok(),
+                _ => option.vars.push(value),
+            }
+        }
+        if option.name.is_empty() || option.option_type.is_empty() {
+            return None;
+        }
+        Some(option)
+    }
+}
+
+/// Drives an external engine speaking UCI, run as a subprocess.
+pub struct UciClient {
+    process: Child,
+    stdin: ChildStdin,
+    /// The lines written by the engine, read on a separate thread.
+    lines: Receiver<String>,
+    name: Option<String>,
+    author: Option<String>,
+    options: Vec<EngineOption>,
+    /// The position last sent, to read the moves of the engine.
+    board: Board,
+}
+
+impl UciClient {
+    /// Starts the engine at `program` and performs the handshake.
+    pub fn spawn(program: impl AsRef<OsStr>) -> Result<UciClient, Error> {
+        UciClient::spawn_command(Command::new(program))
+    }
+
+    /// Starts the engine with `command`, e.g. to pass arguments, and performs the handshake.
+    pub fn spawn_command(mut command: Command) -> Result<UciClient, Error> {
+        let mut process = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin = process.stdin.take().unwrap();
+        let stdout = process.stdout.take().unwrap();
+        let (sender, lines) = channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        let mut client = UciClient {
+            process,
+            stdin,
+            lines,
+            name: None,
+            author: None,
+            options: Vec::new(),
+            board: Board::from_initial_position(),
+        };
+        client.handshake()?;
+        Ok(client)
+    }
+
+    /// The name sent with `id name`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The author sent with `id author`.
+    pub fn author(&self) -> Option<&str> {
+        self.author.as_deref()
+    }
+
+    pub fn options(&self) -> &[EngineOption] {
+        &self.options
+    }
+
+    pub fn option(&self, name: &str) -> Option<&EngineOption> {
+        self.options
+            .iter()
+            .find(|option| option.name.eq_ignore_ascii_case(name))
+    }
+
+    /// The position last sent with `set_position`.
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Sets an option declared by the engine. Buttons are pressed with an empty value.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        if self.option(name).is_none() {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("unknown option {}", name),
+            ));
+        }
+        if value.is_empty() {
+            self.send(&format!("setoption name {}", name))
+        } else {
+            self.send(&format!("setoption name {} value {}", name, value))
+        }
+    }
+
+    /// Waits until the engine has processed the previous commands.
+    pub fn is_ready(&mut self) -> Result<(), Error> {
+        self.send("isready")?;
+        while self.read_line(Some(HANDSHAKE_TIMEOUT))? != "readyok" {}
+        Ok(())
+    }
+
+    /// Tells the engine that the next position is from a new game.
+    pub fn new_game(&mut self) -> Result<(), Error> {
+        self.send("ucinewgame")?;
+        self.is_ready()
+    }
+
+    /// Sends the position reached by playing `moves` from `board`.
+    pub fn set_position(&mut self, board: &Board, moves: &[Move]) -> Result<(), Error> {
+        let mut command = format!("position fen {}", to_string(board, 1));
+        let mut position = *board;
+        if !moves.is_empty() {
+            command += " moves";
+        }
+        for &m in moves {
+            command += " ";
+            command += &m.to_uci(&position);
+            position.make_move(m);
+        }
+        self.send(&command)?;
+        self.board = position;
+        Ok(())
+    }
+
+    /// Searches the position last sent and returns the best move.
+    pub fn go(&mut self, go: &GoCommand) -> Result<BestMove, Error> {
+        self.go_with_info(go, |_| {})
+    }
+
+    /// Searches the position last sent, calling `on_info` for every `info` line, and returns
+    /// the best move.
+    pub fn go_with_info(
+        &mut self,
+        go: &GoCommand,
+        on_info: impl FnMut(&Info),
+    ) -> Result<BestMove, Error> {
+        self.start_search(go)?;
+        self.wait_for_best_move(None, on_info)
+    }
+
+    /// Starts searching the position last sent without waiting for the result, e.g. for an
+    /// infinite search ended with `stop`.
+    pub fn start_search(&mut self, go: &GoCommand) -> Result<(), Error> {
+        self.send(&go.to_string())
+    }
+
+    pub fn stop(&mut self) -> Result<(), Error> {
+        self.send("stop")
+    }
+
+    pub fn ponderhit(&mut self) -> Result<(), Error> {
+        self.send("ponderhit")
+    }
+
+    /// Reads the lines of a running search until `bestmove`, calling `on_info` for every
+    /// `info` line. With a `timeout`, gives up with a `TimedOut` error once it has passed,
+    /// leaving the search running.
+    pub fn wait_for_best_move(
+        &mut self,
+        timeout: Option<Duration>,
+        mut on_info: impl FnMut(&Info),
+    ) -> Result<BestMove, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let remaining =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            let line = self.read_line(remaining)?;
+            let (command, params) = line.split_once(' ').unwrap_or((&line, ""));
+            match command {
+                "info" => on_info(&Info::parse(params, &self.board)),
+                "bestmove" => {
+                    return BestMove::parse(params, &self.board).ok_or_else(|| {
+                        Error::new(ErrorKind::InvalidData, format!("illegal {}", line))
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Asks the engine to exit and waits for it.
+    pub fn quit(mut self) -> Result<ExitStatus, Error> {
+        self.send("quit")?;
+        self.process.wait()
+    }
+
+    fn handshake(&mut self) -> Result<(), Error> {
+        self.send("uci")?;
+        loop {
+            let line = self.read_line(Some(HANDSHAKE_TIMEOUT))?;
+            let (command, params) = line.split_once(' ').unwrap_or((&line, ""));
+            match command {
+                "id" => match params.split_once(' ') {
+                    Some(("name", name)) => self.name = Some(name.trim().to_string()),
+                    Some(("author", author)) => self.author = Some(author.trim().to_string()),
+                    _ => {}
+                },
+                "option" => self.options.extend(EngineOption::parse(params)),
+                "uciok" => return Ok(()),
+                _ => {}
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), Error> {
+        writeln!(self.stdin, "{}", command)?;
+        self.stdin.flush()
+    }
+
+    /// The next line written by the engine, waiting at most `timeout` if any.
+    fn read_line(&mut self, timeout: Option<Duration>) -> Result<String, Error> {
+        let line = match timeout {
+            Some(timeout) => self
+                .lines
+                .recv_timeout(timeout)
+                .map_err(|error| match error {
+                    RecvTimeoutError::Timeout => ErrorKind::TimedOut,
+                    RecvTimeoutError::Disconnected => ErrorKind::UnexpectedEof,
+                }),
+            None => self.lines.recv().map_err(|_| ErrorKind::UnexpectedEof),
+        };
+        line.map(|line| line.trim().to_string())
+            .map_err(|kind| Error::new(kind, "the engine didn't answer"))
+    }
+}
+
+impl Drop for UciClient {
+    fn drop(&mut self) {
+        // the engine may be gone already, after `quit` or a crash
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::uci::client::*;
+    use std::time::Duration;
+
+    #[test]
+    pub fn parse_info() {
+        let board = Board::from_initial_position();
+        let info = Info::parse(
+            "depth 12 seldepth 18 multipv 2 score cp -25 upperbound nodes 123456 nps 1000000 \
+             time 123 hashfull 42 pv e2e4 e7e5 g1f3 a1a8 d2d4",
+            &board,
+        );
+        assert_eq!(info.depth, Some(12));
+        assert_eq!(info.seldepth, Some(18));
+        assert_eq!(info.multipv, Some(2));
+        assert_eq!(info.score, Some(EngineScore::Centipawns(-25)));
+        assert!(info.upperbound && !info.lowerbound);
+        assert_eq!(info.nodes, Some(123456));
+        assert_eq!(info.nps, Some(1000000));
+        assert_eq!(info.time, Some(Duration::from_millis(123)));
+        assert_eq!(info.hashfull, Some(42));
+        // the pv stops at the illegal move
+        let pv: Vec<String> = info.pv.iter().map(|m| m.to_string()).collect();
+        assert_eq!(pv, ["e2e4", "e7e5", "g1f3"]);
+
+        let info = Info::parse("score mate -3 currmove g1f3 string hello  world", &board);
+        assert_eq!(info.score, Some(EngineScore::Mate(-3)));
+        assert_eq!(info.currmove, Move::from_uci("g1f3", &board));
+        assert_eq!(info.string.as_deref(), Some("hello world"));
+    }
+
+    #[test]
+    pub fn parse_best_move() {
+        let board = Board::from_initial_position();
+        let best_move = BestMove::parse("e2e4 ponder e7e5", &board).unwrap();
+        assert_eq!(best_move.best_move, Move::from_uci("e2e4", &board));
+        assert_eq!(best_move.ponder.unwrap().to_string(), "e7e5");
+        assert_eq!(BestMove::parse("e2e5", &board), None);
+
+        let mated = board_from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(
+            BestMove::parse("0000", &mated),
+            Some(BestMove {
+                best_move: None,
+                ponder: None
+            })
+        );
+    }
+
+    #[test]
+    pub fn parse_option() {
+        assert_eq!(
+            EngineOption::parse("name Hash type spin default 16 min 1 max 1024"),
+            Some(EngineOption {
+                name: String::from("Hash"),
+                option_type: String::from("spin"),
+                default: Some(String::from("16")),
+                min: Some(1),
+                max: Some(1024),
+                vars: vec![],
+            })
+        );
+        let option = EngineOption::parse(
+            "name Play Style type combo default Solid var Solid var Very Risky",
+        )
+        .unwrap();
+        assert_eq!(option.name, "Play Style");
+        assert_eq!(option.vars, ["Solid", "Very Risky"]);
+        assert_eq!(EngineOption::parse("type check default false"), None);
+    }
+}
@@ -0,0 +1,490 @@
+use crate::core::board::Board;
+use crate::core::fen::board_from_fen;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::hash;
+use crate::eval::hand_crafted::HandCraftedEvaluator;
+use crate::search::bench::{bench, BENCH_DEPTH};
+use crate::search::searcher::Searcher;
+use crate::search::transposition_table::TranspositionTable;
+use crate::uci::*;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+pub const MAX_HASH_MB: usize = 65536;
+pub const MAX_THREADS: usize = 256;
+pub const MAX_MULTI_PV: usize = 256;
+
+/// Options set by the GUI with `setoption`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UciOptions {
+    pub hash_mb: usize,
+    pub threads: usize,
+    pub multi_pv: usize,
+    pub ponder: bool,
+    pub chess960: bool,
+}
+
+impl Default for UciOptions {
+    fn default() -> Self {
+        UciOptions {
+            hash_mb: TranspositionTable::DEFAULT_SIZE_MB,
+            threads: 1,
+            multi_pv: 1,
+            ponder: false,
+            chess960: false,
+        }
+    }
+}
+
+type EngineSearcher = Searcher<HandCraftedEvaluator>;
+
+/// A chess engine speaking UCI. Commands are handled one line at a time, searches run on a
+/// separate thread so that `stop`, `ponderhit` and `isready` are answered while searching.
+pub struct UciEngine<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    options: UciOptions,
+    board: Board,
+    /// Hashes of the positions played before `board`, oldest first.
+    history: Vec<u64>,
+    /// The searcher, while no search is running.
+    searcher: Option<EngineSearcher>,
+    search: Option<JoinHandle<EngineSearcher>>,
+    stop: Arc<AtomicBool>,
+    /// Raised while pondering, lowered on `ponderhit`.
+    ponder: Arc<AtomicBool>,
+}
+
+impl<W: Write + Send + 'static> UciEngine<W> {
+    pub fn new(out: W) -> UciEngine<W> {
+        let options = UciOptions::default();
+        let tt = Arc::new(TranspositionTable::new(options.hash_mb));
+        let searcher = Searcher::with_transposition_table(HandCraftedEvaluator::default(), tt);
+        UciEngine {
+            out: Arc::new(Mutex::new(out)),
+            stop: searcher.stop_flag(),
+            ponder: searcher.ponder_flag(),
+            options,
+            board: Board::from_initial_position(),
+            history: Vec::new(),
+            searcher: Some(searcher),
+            search: None,
+        }
+    }
+
+    pub fn options(&self) -> &UciOptions {
+        &self.options
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    /// Handles a command line, returns `false` on `quit`.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, params) = line.split_once(' ').unwrap_or((line, ""));
+        match command {
+            "uci" => self.uci(),
+            "isready" => self.send("readyok"),
+            "ucinewgame" => {
+                self.wait_for_search();
+                self.new_table();
+                self.board = Board::from_initial_position();
+                self.history.clear();
+            }
+            "setoption" => self.set_option(params),
+            "position" => {
+                self.wait_for_search();
+                if self.position(params).is_err() {
+                    self.send(&format!("info string invalid position {}", params));
+                }
+            }
+            "go" => self.go(&GoCommand::parse(params)),
+            "stop" => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
+            }
+            "ponderhit" => self.ponderhit(),
+            "bench" => {
+                self.wait_for_search();
+                let depth = params.parse().unwrap_or(BENCH_DEPTH);
+                let parameters = *self.searcher.as_ref().unwrap().parameters();
+                let result = bench(parameters, depth);
+                self.send(&format!(
+                    "info string bench nodes {} time {} nps {}",
+                    result.nodes,
+                    result.elapsed.as_millis(),
+                    result.nps()
+                ));
+            }
+            "quit" => {
+                self.quit();
+                return false;
+            }
+            "" => {}
+            _ => self.send(&format!("info string unknown command {}", line)),
+        }
+        true
+    }
+
+    /// Stops the search, if any, after it sent its best move.
+    pub fn quit(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.wait_for_search();
+    }
+
+    fn send(&self, line: &str) {
+        send(&mut *self.out.lock().unwrap(), line);
+    }
+
+    fn uci(&self) {
+        let lines = [
+            format!("id name chess-core {}", env!("CARGO_PKG_VERSION")),
+            String::from("id author the chess-core developers"),
+            format!(
+                "option name Hash type spin default {} min 1 max {}",
+                TranspositionTable::DEFAULT_SIZE_MB,
+                MAX_HASH_MB
+            ),
+            format!(
+                "option name Threads type spin default 1 min 1 max {}",
+                MAX_THREADS
+            ),
+            format!(
+                "option name MultiPV type spin default 1 min 1 max {}",
+                MAX_MULTI_PV
+            ),
+            String::from("option name Ponder type check default false"),
+            String::from("option name UCI_Chess960 type check default false"),
+            String::from("uciok"),
+        ];
+        for line in lines {
+            self.send(&line);
+        }
+    }
+
+    fn set_option(&mut self, params: &str) {
+        let params = params.trim().strip_prefix("name ").unwrap_or(params);
+        let (name, value) = match params.split_once(" value ") {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => (params.trim(), ""),
+        };
+        let number = value.parse::<usize>().ok();
+        match name.to_ascii_lowercase().as_str() {
+            "hash" if number.is_some() => {
+                self.options.hash_mb = number.unwrap().clamp(1, MAX_HASH_MB);
+                self.wait_for_search();
+                self.new_table();
+            }
+            "threads" if number.is_some() => {
+                self.options.threads = number.unwrap().clamp(1, MAX_THREADS);
+                self.wait_for_search();
+                if let Some(searcher) = self.searcher.as_mut() {
+                    searcher.set_threads(self.options.threads);
+                }
+            }
+            "multipv" if number.is_some() => {
+                self.options.multi_pv = number.unwrap().clamp(1, MAX_MULTI_PV);
+                self.wait_for_search();
+                if let Some(searcher) = self.searcher.as_mut() {
+                    searcher.set_multi_pv(self.options.multi_pv);
+                }
+            }
+            "ponder" => self.options.ponder = value == "true",
+            "uci_chess960" => self.options.chess960 = value == "true",
+            _ => self.send(&format!("info string unknown option {}", params)),
+        }
+    }
+
+    fn new_table(&mut self) {
+        let tt = Arc::new(TranspositionTable::new(self.options.hash_mb));
+        if let Some(searcher) = self.searcher.as_mut() {
+            searcher.set_transposition_table(tt);
+        }
+    }
+
+    fn position(&mut self, params: &str) -> Result<(), ()> {
+        let (position, moves) = match params.split_once("moves") {
+            Some((position, moves)) => (position.trim(), moves),
+            None => (params.trim(), ""),
+        };
+        let mut board = if position == "startpos" {
+            Board::from_initial_position()
+        } else {
+            let fen = position.strip_prefix("fen").ok_or(())?;
+            board_from_fen(fen.trim()).ok_or(())?
+        };
+        if self.options.chess960 {
+            board.set_chess960(true);
+        }
+        let mut history = Vec::new();
+        for uci in moves.split_whitespace() {
+            let m = Move::from_uci(uci, &board).ok_or(())?;
+            history.push(hash(&board));
+            board.make_move(m);
+        }
+        self.board = board;
+        self.history = history;
+        Ok(())
+    }
+
+    fn go(&mut self, go: &GoCommand) {
+        self.wait_for_search();
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.set_game_history(self.history.clone());
+        let mut limits = go.limits(self.board.side_to_play);
+        limits.search_moves = go.search_moves(&self.board);
+        self.stop.store(false, Ordering::Relaxed);
+        self.ponder.store(go.ponder, Ordering::Relaxed);
+
+        let board = self.board;
+        let out = self.out.clone();
+        self.search = Some(std::thread::spawn(move || {
+            let tt = searcher.transposition_table().clone();
+            let result = searcher.search_with_info(&board, &limits, |result| {
+                for line in info_lines(&board, result, tt.hashfull()) {
+                    send(&mut *out.lock().unwrap(), &line);
+                }
+            });
+            let line = match result.best_move {
+                None => String::from("bestmove 0000"),
+                Some(best_move) => {
+                    let mut line = format!("bestmove {}", best_move.to_uci(&board));
+                    if let Some(&reply) = result.pv.get(1) {
+                        let mut next = board;
+                        next.make_move(best_move);
+                        line += &format!(" ponder {}", reply.to_uci(&next));
+                    }
+                    line
+                }
+            };
+            send(&mut *out.lock().unwrap(), &line);
+            searcher
+        }));
+    }
+
+    /// The opponent played the expected move: the search goes on as a normal search, its
+    /// clock started from now.
+    fn ponderhit(&mut self) {
+        self.ponder.store(false, Ordering::Relaxed);
+    }
+
+    fn wait_for_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            self.searcher = Some(search.join().unwrap());
+        }
+    }
+}
+
+/// Runs an engine reading commands from `input` until `quit` or the end of the input.
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) {
+    let mut engine = UciEngine::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle(&line) {
+            return;
+        }
+    }
+    engine.quit();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::to_string;
+    use crate::uci::engine::UciEngine;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+
+        fn wait_for(&self, text: &str) -> bool {
+            self.wait_for_count(text, 1)
+        }
+
+        fn wait_for_count(&self, text: &str, count: usize) -> bool {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(10) {
+                if self.text().matches(text).count() >= count {
+                    return true;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            false
+        }
+    }
+
+    fn engine() -> (UciEngine<Output>, Output) {
+        let output = Output::default();
+        (UciEngine::new(output.clone()), output)
+    }
+
+    #[test]
+    pub fn handshake_and_options() {
+        let (mut engine, output) = engine();
+        assert!(engine.handle("uci"));
+        assert!(engine.handle("isready"));
+        let text = output.text();
+        assert!(text.contains("option name Hash type spin default 16"));
+        assert!(text.contains("option name MultiPV"));
+        assert!(text.ends_with("uciok\nreadyok\n"));
+
+        engine.handle("setoption name Hash value 1");
+        engine.handle("setoption name Threads value 4");
+        engine.handle("setoption name MultiPV value 1000");
+        engine.handle("setoption name UCI_Chess960 value true");
+        let options = engine.options();
+        assert_eq!(
+            (options.hash_mb, options.threads, options.multi_pv),
+            (1, 4, 256)
+        );
+        assert!(options.chess960);
+        engine.handle("setoption name Style value risky");
+        assert!(output.text().contains("info string unknown option"));
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    pub fn position() {
+        let (mut engine, output) = engine();
+        engine.handle("position startpos moves e2e4 e7e5 g1f3");
+        assert_eq!(
+            to_string(engine.board(), 2),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        engine.handle("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1");
+        assert_eq!(
+            to_string(engine.board(), 1),
+            "4k3/8/8/8/8/8/8/5RK1 b - - 1 1"
+        );
+        engine.handle("position startpos moves e2e5");
+        assert!(output.text().contains("info string invalid position"));
+    }
+
+    #[test]
+    pub fn go_depth() {
+        let (mut engine, output) = engine();
+        engine.handle("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle("go depth 3");
+        assert!(output.wait_for("bestmove"));
+        let text = output.text();
+        assert!(text.contains("info depth 1 score"));
+        assert!(text.contains("score mate 1"));
+        assert!(text.contains("hashfull"));
+        assert!(text.contains("bestmove a1a8"));
+    }
+
+    #[test]
+    pub fn bench() {
+        let (mut engine, output) = engine();
+        engine.handle("bench 3");
+        let text = output.text();
+        assert!(text.starts_with("info string bench nodes "), "{}", text);
+        assert!(text.contains(" nps "));
+    }
+
+    #[test]
+    pub fn multi_pv_and_search_moves() {
+        let (mut engine, output) = engine();
+        engine.handle("setoption name MultiPV value 2");
+        engine.handle("position fen 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        engine.handle("go depth 3");
+        assert!(output.wait_for("bestmove"));
+        let text = output.text();
+        assert!(
+            text.contains("info depth 3 multipv 1 score mate 1"),
+            "{}",
+            text
+        );
+        assert!(text.contains("info depth 3 multipv 2 score cp"));
+
+        // setting an option waits for the search
+        engine.handle("setoption name MultiPV value 1");
+        engine.handle("go depth 3 searchmoves h2h3 g2g3");
+        engine.handle("setoption name MultiPV value 1");
+        let text = output.text();
+        let best_move = text.lines().last().unwrap();
+        assert!(
+            best_move.starts_with("bestmove h2h3") || best_move.starts_with("bestmove g2g3"),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    pub fn go_with_clock() {
+        let (mut engine, output) = engine();
+        engine.handle("position startpos");
+        let start = Instant::now();
+        engine.handle("go wtime 1000 btime 1000 winc 0 binc 0");
+        assert!(output.wait_for("bestmove"));
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+
+    #[test]
+    pub fn stop_infinite_search() {
+        let (mut engine, output) = engine();
+        engine.handle("position startpos");
+        engine.handle("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        engine.handle("isready");
+        assert!(output.text().contains("readyok"));
+        assert!(!output.text().contains("bestmove"));
+        engine.handle("stop");
+        assert!(output.text().contains("bestmove"));
+    }
+
+    #[test]
+    pub fn ponder() {
+        let (mut engine, output) = engine();
+        engine.handle("setoption name Ponder value true");
+        engine.handle("position startpos moves e2e4");
+        engine.handle("go ponder movetime 50");
+        thread::sleep(Duration::from_millis(150));
+        assert!(!output.text().contains("bestmove"));
+        engine.handle("ponderhit");
+        assert!(output.wait_for("bestmove"));
+        assert!(output.text().contains(" ponder "));
+
+        // the depth limit still applies once the opponent played the expected move
+        let before = output.text().len();
+        engine.handle("go ponder depth 2");
+        thread::sleep(Duration::from_millis(100));
+        let text = output.text()[before..].to_string();
+        assert!(text.contains("info depth 2"), "{}", text);
+        assert!(!text.contains("info depth 3") && !text.contains("bestmove"));
+        engine.handle("ponderhit");
+        assert!(output.wait_for_count("bestmove", 2));
+
+        // the clock only runs from ponderhit on, then the search keeps to it
+        engine.handle("go ponder wtime 100 btime 100");
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(output.text().matches("bestmove").count(), 2);
+        let start = Instant::now();
+        engine.handle("ponderhit");
+        assert!(output.wait_for_count("bestmove", 3));
+        assert!(start.elapsed() < Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,274 @@
+use crate::core::board::Board;
+use crate::core::r#move::Move;
+use crate::core::Color;
+use crate::core::Color::*;
+use crate::search::time_manager::Clock;
+use crate::search::*;
+use std::fmt::{Display, Formatter};
+use std::io::Write;
+use std::time::Duration;
+
+pub mod client;
+pub mod engine;
+
+/// The parameters of `go`, which end the moves of `searchmoves`.
+const GO_PARAMETERS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+/// Time kept in reserve for the communication with the GUI.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(30);
+
+/// The parameters of a `go` command.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GoCommand {
+    pub wtime: Option<Duration>,
+    pub btime: Option<Duration>,
+    pub winc: Option<Duration>,
+    pub binc: Option<Duration>,
+    pub movestogo: Option<u32>,
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub movetime: Option<Duration>,
+    pub infinite: bool,
+    pub ponder: bool,
+    /// Root moves to restrict the search to, in UCI notation.
+    pub searchmoves: Vec<String>,
+}
+
+impl GoCommand {
+    /// Parses the parameters following `go`. Unknown parameters and malformed values are
+    /// skipped.
+    pub fn parse(params: &str) -> GoCommand {
+        let mut go = GoCommand::default();
+        let mut tokens = params.split_whitespace().peekable();
+        while let Some(token) = tokens.next() {
+            if token == "searchmoves" {
+                while let Some(m) = tokens.next_if(|token| !GO_PARAMETERS.contains(token)) {
+                    go.searchmoves.push(m.to_string());
+                }
+                continue;
+            }
+            let mut value = || tokens.next().and_then(|value| value.parse::<u64>().ok());
+            match token {
+                "wtime" => go.wtime = value().map(Duration::from_millis),
+                "btime" => go.btime = value().map(Duration::from_millis),
+                "winc" => go.winc = value().map(Duration::from_millis),
+                "binc" => go.binc = value().map(Duration::from_millis),
+                "movestogo" => go.movestogo = value().map(|moves| moves as u32),
+                "depth" => go.depth = value().map(|depth| depth as u32),
+                "nodes" => go.nodes = value(),
+                "movetime" => go.movetime = value().map(Duration::from_millis),
+                "infinite" => go.infinite = true,
+                "ponder" => go.ponder = true,
+                _ => {}
+            }
+        }
+        go
+    }
+
+    /// The clock of `color`, if the GUI sent its remaining time.
+    pub fn clock(&self, color: Color) -> Option<Clock> {
+        let (remaining, increment) = match color {
+            WHITE => (self.wtime?, self.winc.unwrap_or_default()),
+            BLACK => (self.btime?, self.binc.unwrap_or_default()),
+        };
+        Some(Clock {
+            remaining,
+            increment,
+            moves_to_go: self.movestogo,
+            move_overhead: MOVE_OVERHEAD,
+        })
+    }
+
+    /// The search limits for `color` to play. While pondering, the searcher only starts
+    /// counting the time on `ponderhit`. An exact `movetime` overrides the clock.
+    pub fn limits(&self, color: Color) -> SearchLimits {
+        SearchLimits {
+            depth: self.depth,
+            nodes: self.nodes,
+            movetime: self.movetime,
+            clock: if self.movetime.is_some() {
+                None
+            } else {
+                self.clock(color)
+            },
+            infinite: self.infinite,
+            search_moves: Vec::new(),
+        }
+    }
+
+    /// The legal moves of `searchmoves` in `board`.
+    pub fn search_moves(&self, board: &Board) -> Vec<Move> {
+        self.searchmoves
+            .iter()
+            .filter_map(|m| Move::from_uci(m, board))
+            .collect()
+    }
+}
+
+impl Display for GoCommand {
+    /// Writes the command as sent by a GUI, e.g. `go wtime 60000 btime 60000 movestogo 20`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("go")?;
+        let millis = [
+            ("wtime", self.wtime),
+            ("btime", self.btime),
+            ("winc", self.winc),
+            ("binc", self.binc),
+        ];
+        for (name, value) in millis {
+            if let Some(value) = value {
+                write!(f, " {} {}", name, value.as_millis())?;
+            }
+        }
+        if let Some(movestogo) = self.movestogo {
+            write!(f, " movestogo {}", movestogo)?;
+        }
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
+        if let Some(movetime) = self.movetime {
+            write!(f, " movetime {}", movetime.as_millis())?;
+        }
+        if self.infinite {
+            f.write_str(" infinite")?;
+        }
+        if self.ponder {
+            f.write_str(" ponder")?;
+        }
+        if !self.searchmoves.is_empty() {
+            write!(f, " searchmoves {}", self.searchmoves.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the score as `cp <centipawns>` or `mate <moves>`.
+pub fn format_score(score: i32) -> String {
+    match mate_in_moves(score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", score),
+    }
+}
+
+/// Formats the `info` lines for the result of an iteration searched from `board`: one per
+/// line of a MultiPV search, numbered from 1 by `multipv`.
+pub fn info_lines(board: &Board, result: &SearchResult, hashfull: u32) -> Vec<String> {
+    let millis = result.elapsed.as_millis() as u64;
+    let nps = result.nodes * 1000 / millis.max(1);
+    result
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let multipv = if result.lines.len() > 1 {
+                format!(" multipv {}", i + 1)
+            } else {
+                String::new()
+            };
+            let mut board = *board;
+            let mut pv = Vec::with_capacity(line.pv.len());
+            for &m in line.pv.iter() {
+                pv.push(m.to_uci(&board));
+                board.make_move(m);
+            }
+            format!(
+                "info depth {}{} score {} nodes {} nps {} time {} hashfull {} pv {}",
+                result.depth,
+                multipv,
+                format_score(line.score),
+                result.nodes,
+                nps,
+                millis,
+                hashfull,
+                pv.join(" ")
+            )
+        })
+        .collect()
+}
+
+/// Sends a line to the GUI of an engine, UCI or xboard.
+pub fn send(out: &mut impl Write, line: &str) {
+    // the GUI is gone when the output is closed, there is no one to report to
+    let _ = writeln!(out, "{}", line);
+    let _ = out.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::search::*;
+    use crate::uci::*;
+    use std::time::Duration;
+
+    #[test]
+    pub fn parse_go() {
+        let go = GoCommand::parse("wtime 60000 btime 30000 winc 1000 binc 500 movestogo 20");
+        assert_eq!(go.wtime, Some(Duration::from_secs(60)));
+        assert_eq!(go.binc, Some(Duration::from_millis(500)));
+        assert_eq!(go.movestogo, Some(20));
+        assert_eq!(go.clock(BLACK).unwrap().remaining, Duration::from_secs(30));
+        assert_eq!(go.clock(BLACK).unwrap().moves_to_go, Some(20));
+
+        let go = GoCommand::parse("depth 7 nodes 1000 infinite");
+        assert_eq!(go.clock(WHITE), None);
+        assert_eq!(
+            go.limits(BLACK),
+            SearchLimits {
+                depth: Some(7),
+                nodes: Some(1000),
+                movetime: None,
+                clock: None,
+                infinite: true,
+                search_moves: vec![],
+            }
+        );
+        // the searcher holds the time limits back until ponderhit
+        let limits = GoCommand::parse("ponder wtime 1000 movetime 100").limits(WHITE);
+        assert!(!limits.infinite);
+        assert_eq!(limits.movetime, Some(Duration::from_millis(100)));
+        assert_eq!(limits.clock, None);
+        let limits = GoCommand::parse("ponder depth 5 nodes 1000 wtime 1000").limits(WHITE);
+        assert_eq!(limits.depth, Some(5));
+        assert_eq!(limits.nodes, Some(1000));
+        assert_eq!(limits.clock.unwrap().remaining, Duration::from_secs(1));
+        let limits = GoCommand::parse("wtime 1000 btime 2000 winc 10").limits(BLACK);
+        assert_eq!(limits.clock.unwrap().remaining, Duration::from_secs(2));
+        assert_eq!(limits.clock.unwrap().increment, Duration::ZERO);
+        assert_eq!(GoCommand::parse("depth x"), GoCommand::default());
+
+        let text = "go wtime 60000 btime 30000 winc 1000 binc 500 movestogo 20";
+        assert_eq!(GoCommand::parse(&text[3..]).to_string(), text);
+        let text = "go depth 7 nodes 1000 movetime 250 infinite ponder";
+        assert_eq!(GoCommand::parse(&text[3..]).to_string(), text);
+
+        let go = GoCommand::parse("searchmoves e2e4 d2d4 e7e5 infinite");
+        assert_eq!(go.searchmoves, ["e2e4", "d2d4", "e7e5"]);
+        assert!(go.infinite);
+        let moves = go.search_moves(&Board::from_initial_position());
+        assert_eq!(moves.len(), 2);
+        assert_eq!(go.to_string(), "go infinite searchmoves e2e4 d2d4 e7e5");
+    }
+
+    #[test]
+    pub fn scores() {
+        assert_eq!(format_score(35), "cp 35");
+        assert_eq!(format_score(mate_in(3)), "mate 2");
+        assert_eq!(format_score(mated_in(2)), "mate -1");
+    }
+}
@@ -0,0 +1,477 @@
+use crate::core::board::Board;
+use crate::core::fen::board_from_fen;
+use crate::core::r#move::Move;
+use crate::core::zobrist_hash::hash;
+use crate::core::Color;
+use crate::eval::hand_crafted::HandCraftedEvaluator;
+use crate::search::searcher::Searcher;
+use crate::uci::{send, GoCommand};
+use crate::xboard::*;
+use std::io::{BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+type EngineSearcher = Searcher<HandCraftedEvaluator>;
+
+/// A chess engine speaking the XBoard/CECP protocol, version 2. Searches run on a separate
+/// thread so that `?`, `force` and other commands are handled while thinking.
+pub struct XBoardEngine<W: Write + Send + 'static> {
+    out: Arc<Mutex<W>>,
+    board: Board,
+    /// The positions before each move of the game, to take moves back.
+    undo: Vec<Board>,
+    /// The side played by the engine, none in force mode.
+    engine_color: Option<Color>,
+    post: bool,
+    level: Option<Level>,
+    /// Exact time per move set by `st`.
+    move_time: Option<Duration>,
+    /// Depth limit set by `sd`.
+    depth: Option<u32>,
+    time: Option<Duration>,
+    opponent_time: Option<Duration>,
+    /// The searcher, while no search is running.
+    searcher: Option<EngineSearcher>,
+    /// The running search, which returns the move it played.
+    search: Option<JoinHandle<(EngineSearcher, Option<Move>)>>,
+    stop: Arc<AtomicBool>,
+    /// Raised to drop the move of the running search instead of playing it.
+    discard: Arc<AtomicBool>,
+}
+
+impl<W: Write + Send + 'static> XBoardEngine<W> {
+    pub fn new(out: W) -> XBoardEngine<W> {
+        let searcher = Searcher::new(HandCraftedEvaluator::default());
+        XBoardEngine {
+            out: Arc::new(Mutex::new(out)),
+            board: Board::from_initial_position(),
+            undo: Vec::new(),
+            engine_color: Some(BLACK),
+            post: false,
+            level: None,
+            move_time: None,
+            depth: None,
+            time: None,
+            opponent_time: None,
+            stop: searcher.stop_flag(),
+            searcher: Some(searcher),
+            search: None,
+            discard: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// The current position, once the running search is over.
+    pub fn board(&mut self) -> &Board {
+        self.wait_for_search();
+        &self.board
+    }
+
+    /// Handles a command line, returns `false` on `quit`.
+    pub fn handle(&mut self, line: &str) -> bool {
+        let line = line.trim();
+        let (command, params) = line.split_once(' ').unwrap_or((line, ""));
+        let params = params.trim();
+        match command {
+            "protover" => self.features(),
+            "new" => {
+                self.abort_search();
+                self.set_board(Board::from_initial_position());
+                self.engine_color = Some(BLACK);
+                self.move_time = None;
+                self.depth = None;
+            }
+            "force" => {
+                self.abort_search();
+                self.engine_color = None;
+            }
+            "go" => {
+                self.wait_for_search();
+                self.engine_color = Some(self.board.side_to_play);
+                self.think();
+            }
+            "playother" => {
+                self.abort_search();
+                self.engine_color = Some(self.board.side_to_play.opposite());
+            }
+            "usermove" => self.user_move(params),
+            "setboard" => {
+                self.abort_search();
+                match board_from_fen(params) {
+                    Some(board) => self.set_board(board),
+                    None => self.send("tellusererror Illegal position"),
+                }
+            }
+            "level" => match Level::parse(params) {
+                Some(level) => {
+                    self.level = Some(level);
+                    self.move_time = None;
+                }
+                None => self.send(&format!("Error (bad level): {}", params)),
+            },
+            "st" => self.move_time = params.parse().ok().map(Duration::from_secs),
+            "sd" => self.depth = params.parse().ok(),
+            "time" => self.time = centiseconds(params),
+            "otim" => self.opponent_time = centiseconds(params),
+            "undo" => {
+                self.abort_search();
+                self.take_back(1);
+            }
+            "remove" => {
+                self.abort_search();
+                self.take_back(2);
+            }
+            "result" => {
+                self.abort_search();
+                self.engine_color = None;
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            "ping" => {
+                self.wait_for_search();
+                self.send(&format!("pong {}", params));
+            }
+            "?" => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.wait_for_search();
+            }
+            "quit" => {
+                self.abort_search();
+                return false;
+            }
+            "xboard" | "accepted" | "rejected" | "random" | "easy" | "hard" | "computer"
+            | "name" | "rating" | "ics" | "" => {}
+            // moves without `usermove`, as sent before the feature is accepted
+            _ if looks_like_move(command) => self.user_move(command),
+            _ => self.send(&format!("Error (unknown command): {}", command)),
+        }
+        true
+    }
+
+    fn features(&self) {
+        self.send(&format!(
+            "feature ping=1 setboard=1 playother=1 usermove=1 time=1 draw=0 sigint=0 sigterm=0 \
+             reuse=1 analyze=0 colors=0 san=0 myname=\"chess-core {}\"",
+            env!("CARGO_PKG_VERSION")
+        ));
+        self.send("feature done=1");
+    }
+
+    fn send(&self, line: &str) {
+        send(&mut *self.out.lock().unwrap(), line);
+    }
+
+    fn set_board(&mut self, board: Board) {
+        self.board = board;
+        self.undo.clear();
+    }
+
+    fn take_back(&mut self, moves: usize) {
+        for _ in 0..moves {
+            if let Some(board) = self.undo.pop() {
+                self.board = board;
+            }
+        }
+    }
+
+    fn history(&self) -> Vec<u64> {
+        self.undo.iter().map(hash).collect()
+    }
+
+    /// Parses a move in coordinate notation or SAN, once the running search is over.
+    fn parse_move(&mut self, text: &str) -> Option<Move> {
+        self.wait_for_search();
+        Move::from_uci(text, &self.board).or_else(|| Move::from_san(text, &self.board))
+    }
+
+    fn user_move(&mut self, text: &str) {
+        let Some(m) = self.parse_move(text) else {
+            self.send(&format!("Illegal move: {}", text));
+            return;
+        };
+        self.undo.push(self.board);
+        self.board.make_move(m);
+        if self.engine_color == Some(self.board.side_to_play) {
+            self.think();
+        }
+    }
+
+    fn go_command(&self, color: Color) -> GoCommand {
+        let moves_played = self
+            .undo
+            .iter()
+            .filter(|board| board.side_to_play == color)
+            .count() as u32;
+        let base = self.level.map(|level| level.base);
+        let (time, opponent_time) = (self.time.or(base), self.opponent_time.or(base));
+        let (wtime, btime) = match color {
+            WHITE => (time, opponent_time),
+            BLACK => (opponent_time, time),
+        };
+        let increment = self.level.map(|level| level.increment);
+        GoCommand {
+            wtime,
+            btime,
+            winc: increment,
+            binc: increment,
+            movestogo: self
+                .level
+                .filter(|level| level.moves_per_session > 0)
+                .map(|level| level.moves_per_session - moves_played % level.moves_per_session),
+            depth: self.depth,
+            movetime: self.move_time,
+            ..GoCommand::default()
+        }
+    }
+
+    /// Starts searching a move for the side to play, or claims the result if the game is over.
+    fn think(&mut self) {
+        let history = self.history();
+        if let Some(result) = game_result(&self.board, &history) {
+            self.send(result);
+            return;
+        }
+        let mut searcher = self.searcher.take().unwrap();
+        searcher.set_game_history(history.clone());
+        let limits = self
+            .go_command(self.board.side_to_play)
+            .limits(self.board.side_to_play);
+        self.stop.store(false, Ordering::Relaxed);
+        self.discard.store(false, Ordering::Relaxed);
+
+        let board = self.board;
+        let post = self.post;
+        let out = self.out.clone();
+        let discard = self.discard.clone();
+        self.search = Some(std::thread::spawn(move || {
+            let result = searcher.search_with_info(&board, &limits, |result| {
+                if post {
+                    send(&mut *out.lock().unwrap(), &thinking_line(result));
+                }
+            });
+            // decided under the lock, so that a move is either sent and played or neither
+            let mut out = out.lock().unwrap();
+            if discard.load(Ordering::Relaxed) {
+                return (searcher, None);
+            }
+            let best_move = result.best_move.unwrap();
+            send(&mut *out, &format!("move {}", best_move));
+            let mut history = history;
+            history.push(hash(&board));
+            let mut next = board;
+            next.make_move(best_move);
+            if let Some(result) = game_result(&next, &history) {
+                send(&mut *out, result);
+            }
+            (searcher, Some(best_move))
+        }));
+    }
+
+    /// Stops the running search without playing its move.
+    fn abort_search(&mut self) {
+        if self.search.is_some() {
+            let _out = self.out.lock().unwrap();
+            self.discard.store(true, Ordering::Relaxed);
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        self.wait_for_search();
+    }
+
+    /// Waits for the running search and plays its move.
+    fn wait_for_search(&mut self) {
+        if let Some(search) = self.search.take() {
+            let (searcher, played) = search.join().unwrap();
+            self.searcher = Some(searcher);
+            if let Some(m) = played {
+                self.undo.push(self.board);
+                self.board.make_move(m);
+            }
+        }
+    }
+}
+
+/// Runs an engine reading commands from `input` until `quit` or the end of the input.
+pub fn run(input: impl BufRead, output: impl Write + Send + 'static) {
+    let mut engine = XBoardEngine::new(output);
+    for line in input.lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.handle(&line) {
+            break;
+        }
+    }
+}
+
+/// Whether `text` may be a move in coordinate notation or SAN, e.g. `e7e8q`, `Nxf3+` or
+/// `O-O`, which tells moves from unknown commands without waiting for the running search.
+fn looks_like_move(text: &str) -> bool {
+    let castling = ["O-O", "O-O-O", "0-0", "0-0-0"].contains(&text.trim_end_matches(['+', '#']));
+    castling
+        || (text.chars().any(|c| ('1'..='8').contains(&c))
+            && text
+                .chars()
+                .all(|c| "abcdefgh12345678NBRQKnbrqx=+#".contains(c)))
+}
+
+fn centiseconds(value: &str) -> Option<Duration> {
+    value
+        .parse()
+        .ok()
+        .map(|cs: u64| Duration::from_millis(cs * 10))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::fen::to_string;
+    use crate::xboard::engine::XBoardEngine;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+
+        fn wait_for(&self, text: &str) -> bool {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(10) {
+                if self.text().contains(text) {
+                    return true;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            false
+        }
+    }
+
+    fn engine() -> (XBoardEngine<Output>, Output) {
+        let output = Output::default();
+        (XBoardEngine::new(output.clone()), output)
+    }
+
+    #[test]
+    pub fn protover() {
+        let (mut engine, output) = engine();
+        assert!(engine.handle("xboard"));
+        engine.handle("protover 2");
+        let text = output.text();
+        assert!(text.contains("feature ping=1 setboard=1"));
+        assert!(text.contains("usermove=1"));
+        assert!(text.ends_with("feature done=1\n"));
+        engine.handle("ping 7");
+        assert!(output.text().ends_with("pong 7\n"));
+        engine.handle("hello");
+        assert!(output.text().ends_with("Error (unknown command): hello\n"));
+        assert!(!engine.handle("quit"));
+    }
+
+    #[test]
+    pub fn replies_to_user_moves() {
+        let (mut engine, output) = engine();
+        engine.handle("new");
+        engine.handle("sd 2");
+        engine.handle("usermove e2e4");
+        assert!(output.wait_for("move "));
+        engine.handle("ping 1");
+        assert!(output.text().ends_with("pong 1\n"));
+        assert_eq!(engine.board().side_to_play, crate::core::Color::WHITE);
+
+        // moves without `usermove` and in SAN are accepted too
+        engine.handle("Nf3");
+        engine.handle("ping 2");
+        assert_eq!(output.text().matches("move ").count(), 2);
+        assert_eq!(engine.undo.len(), 4);
+    }
+
+    #[test]
+    pub fn force_mode() {
+        let (mut engine, output) = engine();
+        engine.handle("new");
+        engine.handle("force");
+        engine.handle("usermove e2e4");
+        engine.handle("usermove e7e5");
+        engine.handle("usermove e7e5");
+        engine.handle("ping 1");
+        assert_eq!(output.text(), "Illegal move: e7e5\npong 1\n");
+        assert_eq!(
+            to_string(engine.board(), 2),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+        );
+        engine.handle("undo");
+        assert_eq!(engine.board().side_to_play, crate::core::Color::BLACK);
+        engine.handle("remove");
+        engine.handle("remove");
+        assert_eq!(
+            to_string(engine.board(), 2),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 2"
+        );
+        engine.handle("setboard not a fen");
+        assert!(output.text().ends_with("tellusererror Illegal position\n"));
+    }
+
+    #[test]
+    pub fn plays_and_claims_mate() {
+        let (mut engine, output) = engine();
+        engine.handle("new");
+        engine.handle("post");
+        engine.handle("setboard 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle("sd 3");
+        engine.handle("go");
+        assert!(output.wait_for("1-0 {White mates}"));
+        let text = output.text();
+        assert!(text.contains("move a1a8\n"));
+        // thinking lines: depth, mate score, time, nodes and the move
+        assert!(text.lines().any(|line| line.starts_with("1 100001 ")));
+
+        // the game is over, `go` claims the result again
+        engine.handle("go");
+        assert_eq!(
+            text.len() + "1-0 {White mates}\n".len(),
+            output.text().len()
+        );
+    }
+
+    #[test]
+    pub fn move_now() {
+        let (mut engine, output) = engine();
+        engine.handle("new");
+        engine.handle("st 1000");
+        engine.handle("go");
+        let start = Instant::now();
+        thread::sleep(Duration::from_millis(50));
+        // unknown commands are answered at once, without waiting for the move
+        for command in ["hint", "bk", "."] {
+            engine.handle(command);
+            let error = format!("Error (unknown command): {}\n", command);
+            assert!(output.text().ends_with(&error), "{}", output.text());
+        }
+        assert!(!output.text().contains("move "));
+        engine.handle("?");
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(output.text().lines().any(|line| line.starts_with("move ")));
+
+        // a search stopped by `force` doesn't play its move
+        engine.handle("usermove e7e5");
+        engine.handle("force");
+        engine.handle("ping 1");
+        assert_eq!(output.text().matches("move ").count(), 1);
+        assert_eq!(engine.undo.len(), 2);
+    }
+}
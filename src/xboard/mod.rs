@@ -0,0 +1,155 @@
+use crate::core::board::Board;
+use crate::core::move_generator::MoveGenerator;
+use crate::core::zobrist_hash::hash;
+use crate::core::Color::*;
+use crate::search::*;
+use std::time::Duration;
+
+pub mod engine;
+
+/// A time control set by `level`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Level {
+    /// Moves to play before the clock is reset to `base`, 0 for the whole game.
+    pub moves_per_session: u32,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+impl Level {
+    /// Parses the parameters of `level`, e.g. `40 5 0`, `0 2:30 12` or `0 1 0.5`: the moves
+    /// per session, the base time in minutes or minutes and seconds, and the increment in
+    /// seconds.
+    pub fn parse(params: &str) -> Option<Level> {
+        let mut tokens = params.split_whitespace();
+        let moves_per_session = tokens.next()?.parse().ok()?;
+        let base = tokens.next()?;
+        let base = match base.split_once(':') {
+            Some((minutes, seconds)) => {
+                minutes.parse::<u64>().ok()? * 60 + seconds.parse::<u64>().ok()?
+            }
+            None => base.parse::<u64>().ok()? * 60,
+        };
+        let increment = tokens.next()?.parse::<f64>().ok()?;
+        if !increment.is_finite() || increment < 0.0 || tokens.next().is_some() {
+            return None;
+        }
+        Some(Level {
+            moves_per_session,
+            base: Duration::from_secs(base),
+            increment: Duration::from_secs_f64(increment),
+        })
+    }
+}
+
+/// Formats the score of a thinking line: centipawns, or 100000 plus the number of moves to
+/// mate, negated when getting mated.
+pub fn format_score(score: i32) -> i32 {
+    match mate_in_moves(score) {
+        Some(moves) if moves > 0 => 100000 + moves,
+        Some(moves) => -100000 + moves,
+        None => score,
+    }
+}
+
+/// Formats a thinking line for the result of an iteration: depth, score, time in centiseconds,
+/// nodes and principal variation.
+pub fn thinking_line(result: &SearchResult) -> String {
+    let pv: Vec<String> = result.pv.iter().map(|m| m.to_string()).collect();
+    format!(
+        "{} {} {} {} {}",
+        result.depth,
+        format_score(result.score),
+        result.elapsed.as_millis() / 10,
+        result.nodes,
+        pv.join(" ")
+    )
+}
+
+/// The result of the game if it is over in `board`, as sent to claim it. `history` holds the
+/// hashes of the positions played before, to detect repetitions.
+pub fn game_result(board: &Board, history: &[u64]) -> Option<&'static str> {
+    let mut generator = MoveGenerator::new(board);
+    generator.generate();
+    if generator.moves.is_empty() {
+        return Some(if board.checkers().empty() {
+            "1/2-1/2 {Stalemate}"
+        } else if board.side_to_play == WHITE {
+            "0-1 {Black mates}"
+        } else {
+            "1-0 {White mates}"
+        });
+    }
+    if board.half_move_counter >= 100 {
+        return Some("1/2-1/2 {Fifty move rule}");
+    }
+    let current = hash(board);
+    if history.iter().filter(|&&h| h == current).count() >= 2 {
+        return Some("1/2-1/2 {Threefold repetition}");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::board::Board;
+    use crate::core::fen::board_from_fen;
+    use crate::core::r#move::Move;
+    use crate::core::zobrist_hash::hash;
+    use crate::search::*;
+    use crate::xboard::*;
+    use std::time::Duration;
+
+    #[test]
+    pub fn parse_level() {
+        assert_eq!(
+            Level::parse("40 5 0"),
+            Some(Level {
+                moves_per_session: 40,
+                base: Duration::from_secs(300),
+                increment: Duration::ZERO,
+            })
+        );
+        let level = Level::parse("0 2:30 12").unwrap();
+        assert_eq!(level.base, Duration::from_secs(150));
+        assert_eq!(level.increment, Duration::from_secs(12));
+        assert_eq!(
+            Level::parse("0 1 0.5").unwrap().increment,
+            Duration::from_millis(500)
+        );
+        assert_eq!(Level::parse("0 1"), None);
+        assert_eq!(Level::parse("0 x 1"), None);
+        assert_eq!(Level::parse("0 1 -1"), None);
+    }
+
+    #[test]
+    pub fn scores() {
+        assert_eq!(format_score(-35), -35);
+        assert_eq!(format_score(mate_in(3)), 100002);
+        assert_eq!(format_score(mated_in(2)), -100001);
+    }
+
+    #[test]
+    pub fn game_results() {
+        let mated = board_from_fen("R5k1/5ppp/8/8/8/8/8/6K1 b - - 1 1").unwrap();
+        assert_eq!(game_result(&mated, &[]), Some("1-0 {White mates}"));
+        let stalemate = board_from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game_result(&stalemate, &[]), Some("1/2-1/2 {Stalemate}"));
+        let fifty = board_from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 100 80").unwrap();
+        assert_eq!(game_result(&fifty, &[]), Some("1/2-1/2 {Fifty move rule}"));
+
+        let mut board = Board::from_initial_position();
+        let mut history = vec![];
+        for _ in 0..2 {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                assert_eq!(game_result(&board, &history), None);
+                history.push(hash(&board));
+                board.make_move(Move::from_uci(uci, &board).unwrap());
+            }
+        }
+        assert_eq!(
+            game_result(&board, &history),
+            Some("1/2-1/2 {Threefold repetition}")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use chess_core::tournament::openings::openings_from_epd;
+use chess_core::tournament::pgn::parse_pgn;
+use chess_core::tournament::*;
+use chess_core::uci::client::UciClient;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+const ENGINE: &str = env!("CARGO_BIN_EXE_chess-core-uci");
+
+#[test]
+pub fn match_between_engines() {
+    let mut engines = [
+        UciClient::spawn(ENGINE).unwrap(),
+        UciClient::spawn(ENGINE).unwrap(),
+    ];
+    // a won and a drawn position, each played with both colors
+    let openings = openings_from_epd(
+        "6k1/5ppp/8/8/8/8/8/R5K1 w - -\n\
+         8/8/4k3/8/8/3K4/8/3R4 b - -\n",
+    )
+    .unwrap();
+    let config = MatchConfig {
+        openings,
+        games: 4,
+        time_control: TimeControl::parse("2+0.05").unwrap(),
+        adjudication: Adjudication {
+            draw_score: Some(10000),
+            draw_moves: 1,
+            ..Adjudication::default()
+        },
+        sprt: None,
+    };
+    let mut games = Vec::new();
+    let score = run_match(&mut engines, &config, |game, _| games.push(game.clone()));
+    assert_eq!(score.games(), 4);
+    assert_eq!(score.wins, 1);
+    assert_eq!(score.losses, 1);
+    assert_eq!(games[0].result, GameResult::WhiteWins);
+    assert_eq!(games[0].termination, Termination::Checkmate);
+    assert_eq!(games[1].result, GameResult::WhiteWins);
+    assert_eq!(games[2].termination, Termination::DrawAdjudication);
+
+    let pgn = games[0].to_pgn("test", 1);
+    let game = &parse_pgn(&pgn).unwrap()[0];
+    assert_eq!(game.tag("Result"), Some("1-0"));
+    assert_eq!(game.moves, games[0].moves);
+    assert_eq!(game.board, games[0].opening.board);
+}
+
+#[test]
+pub fn engine_ignoring_go_loses_on_time() {
+    // answers the handshake, then never the searches
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "while read line; do case $line in uci) echo uciok;; isready) echo readyok;; \
+         quit) exit;; esac; done",
+    ]);
+    let mut hanging = UciClient::spawn_command(command).unwrap();
+    let mut engine = UciClient::spawn(ENGINE).unwrap();
+    let opening = openings_from_epd("6k1/5ppp/8/8/8/8/8/R5K1 w - -\n").unwrap();
+    let start = Instant::now();
+    let game = play_game(
+        &mut hanging,
+        &mut engine,
+        &opening[0],
+        TimeControl::parse("0.2").unwrap(),
+        &Adjudication::default(),
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert_eq!(game.result, GameResult::BlackWins);
+    assert_eq!(game.termination, Termination::TimeForfeit);
+    assert!(game.moves.is_empty());
+}
+
+#[test]
+pub fn match_binary() {
+    let pgn = std::env::temp_dir().join(format!("chess-core-match-{}.pgn", std::process::id()));
+    let output = Command::new(env!("CARGO_BIN_EXE_chess-core-match"))
+        .args([ENGINE, ENGINE, "--games", "2", "--tc", "1+0.05"])
+        .args(["--draw", "10000", "1", "0", "--sprt", "0", "5", "--pgn"])
+        .arg(&pgn)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("game 2: "), "{}", stdout);
+    assert!(
+        stdout.contains("score 0-0-2, elo 0.0 +/- 0.0, llr 0.00"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.ends_with("sprt: inconclusive\n"));
+    let games = parse_pgn(&std::fs::read_to_string(&pgn).unwrap()).unwrap();
+    std::fs::remove_file(&pgn).unwrap();
+    assert_eq!(games.len(), 2);
+    assert_eq!(games[1].tag("Termination"), Some("adjudication"));
+}
@@ -0,0 +1,93 @@
+use chess_core::core::board::Board;
+use chess_core::core::fen::board_from_fen;
+use chess_core::core::r#move::Move;
+use chess_core::uci::client::{EngineScore, UciClient};
+use chess_core::uci::GoCommand;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+#[test]
+pub fn uci_over_pipe() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_chess-core-uci"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    let mut stdout = BufReader::new(engine.stdout.take().unwrap());
+    let mut read_until = |prefix: &str| {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            assert!(stdout.read_line(&mut line).unwrap() > 0, "{:?}", lines);
+            let done = line.starts_with(prefix);
+            lines.push(line.trim_end().to_string());
+            if done {
+                return lines;
+            }
+        }
+    };
+
+    writeln!(stdin, "uci").unwrap();
+    assert!(read_until("uciok")[0].starts_with("id name"));
+    writeln!(stdin, "isready").unwrap();
+    read_until("readyok");
+
+    writeln!(stdin, "ucinewgame").unwrap();
+    writeln!(stdin, "position startpos moves e2e4 e7e5").unwrap();
+    writeln!(stdin, "go depth 4").unwrap();
+    let lines = read_until("bestmove");
+    assert!(lines.iter().any(|line| line.starts_with("info depth 4 ")));
+    assert!(lines.last().unwrap().starts_with("bestmove "));
+
+    writeln!(stdin, "quit").unwrap();
+    assert!(engine.wait().unwrap().success());
+}
+
+#[test]
+pub fn uci_client() {
+    let mut client = UciClient::spawn(env!("CARGO_BIN_EXE_chess-core-uci")).unwrap();
+    assert!(client.name().unwrap().starts_with("chess-core"));
+    assert_eq!(client.option("hash").unwrap().option_type, "spin");
+    client.set_option("Hash", "1").unwrap();
+    assert!(client.set_option("Contempt", "10").is_err());
+    client.new_game().unwrap();
+
+    let board = Board::from_initial_position();
+    let e4 = Move::from_uci("e2e4", &board).unwrap();
+    let mut next = board;
+    next.make_move(e4);
+    let e5 = Move::from_uci("e7e5", &next).unwrap();
+    client.set_position(&board, &[e4, e5]).unwrap();
+    let mut board = next;
+    board.make_move(e5);
+    let mut infos = Vec::new();
+    let go = GoCommand {
+        depth: Some(3),
+        ..GoCommand::default()
+    };
+    let best_move = client
+        .go_with_info(&go, |info| infos.push(info.clone()))
+        .unwrap();
+    assert_eq!(infos.len(), 3);
+    assert_eq!(infos[2].depth, Some(3));
+    assert_eq!(infos[2].pv[0], best_move.best_move.unwrap());
+    assert_eq!(best_move.ponder, infos[2].pv.get(1).copied());
+    assert_eq!(client.board(), &board);
+
+    let mate = board_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+    client.set_position(&mate, &[]).unwrap();
+    let mut score = None;
+    client.start_search(&GoCommand::parse("infinite")).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    client.stop().unwrap();
+    let best_move = client
+        .wait_for_best_move(None, |info| score = info.score)
+        .unwrap();
+    assert_eq!(best_move.best_move.unwrap().to_string(), "a1a8");
+    assert_eq!(score, Some(EngineScore::Mate(1)));
+
+    assert!(client.quit().unwrap().success());
+}
@@ -0,0 +1,41 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+
+#[test]
+pub fn xboard_over_pipe() {
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_chess-core-xboard"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    let mut stdout = BufReader::new(engine.stdout.take().unwrap());
+    let mut read_until = |prefix: &str| {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            assert!(stdout.read_line(&mut line).unwrap() > 0, "{:?}", lines);
+            let done = line.starts_with(prefix);
+            lines.push(line.trim_end().to_string());
+            if done {
+                return lines;
+            }
+        }
+    };
+
+    writeln!(stdin, "xboard").unwrap();
+    writeln!(stdin, "protover 2").unwrap();
+    assert!(read_until("feature done=1")[0].contains("myname="));
+
+    writeln!(stdin, "new").unwrap();
+    writeln!(stdin, "post").unwrap();
+    writeln!(stdin, "sd 4").unwrap();
+    writeln!(stdin, "usermove e2e4").unwrap();
+    let lines = read_until("move ");
+    assert!(lines.iter().any(|line| line.starts_with("4 ")));
+    writeln!(stdin, "ping 1").unwrap();
+    read_until("pong 1");
+
+    writeln!(stdin, "quit").unwrap();
+    assert!(engine.wait().unwrap().success());
+}